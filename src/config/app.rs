@@ -1,3 +1,4 @@
+use chrono::{DateTime, Utc};
 use serde::Deserialize;
 use sha2::{Digest, Sha256};
 use std::time::Duration;
@@ -18,18 +19,164 @@ pub struct AppConfig {
     pub event_segment_size: Option<usize>,
     #[serde(rename = "event-max-segments")]
     pub event_max_segments: Option<usize>,
+    #[serde(rename = "event-channel-capacity")]
+    pub event_channel_capacity: Option<usize>,
+    #[serde(rename = "sse-retry")]
+    #[serde(default, with = "humantime_serde")]
+    pub sse_retry: Option<Duration>,
+    pub wal: Option<WalConfig>,
+    #[serde(rename = "snapshot-file")]
+    pub snapshot_file: Option<String>,
+    #[serde(default)]
+    pub auth: AuthPolicies,
+    pub presence: Option<PresenceConfig>,
+    pub inbox: Option<InboxConfig>,
 }
 
-#[derive(Default, Debug, Deserialize)]
+#[derive(Default, Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum PresenceAudience {
+    #[default]
+    Team,
+    Admins,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct PresenceConfig {
+    #[serde(default)]
+    pub audience: PresenceAudience,
+    #[serde(rename = "admin-role", default = "default_admin_role")]
+    pub admin_role: String,
+    #[serde(default = "default_presence_debounce", with = "humantime_serde")]
+    pub debounce: Duration,
+}
+
+fn default_admin_role() -> String {
+    "admin".into()
+}
+
+fn default_presence_debounce() -> Duration {
+    Duration::from_secs(5)
+}
+
+#[derive(Default, Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum InboxStoreKind {
+    #[default]
+    Memory,
+    File,
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct InboxConfig {
+    #[serde(default)]
+    pub store: InboxStoreKind,
+    pub path: Option<String>,
+    #[serde(rename = "max-entries", default = "default_inbox_max_entries")]
+    pub max_entries: usize,
+}
+
+fn default_inbox_max_entries() -> usize {
+    500
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum AuthPolicy {
+    Public,
+    Jwt,
+    ApiKey,
+    Role(String),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RouteGroup {
+    Ping,
+    Notify,
+    Events,
+    Ingest,
+    Admin,
+    Metrics,
+}
+
+// Groups left out fall back to the behaviour `auth-required` used to give the
+// whole API, except that ping is public and ingest and metrics only need an
+// API key.
+#[derive(Default, Debug, Clone, Deserialize)]
+pub struct AuthPolicies {
+    pub ping: Option<AuthPolicy>,
+    pub notify: Option<AuthPolicy>,
+    pub events: Option<AuthPolicy>,
+    pub ingest: Option<AuthPolicy>,
+    pub admin: Option<AuthPolicy>,
+    pub metrics: Option<AuthPolicy>,
+}
+
+#[derive(Default, Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum WalSync {
+    #[default]
+    Always,
+    Interval,
+    Os,
+}
+
+#[derive(Default, Debug, Clone, PartialEq, Deserialize)]
+pub struct WalConfig {
+    pub dir: String,
+    #[serde(default)]
+    pub sync: WalSync,
+    #[serde(rename = "sync-interval")]
+    #[serde(default, with = "humantime_serde")]
+    pub sync_interval: Option<Duration>,
+}
+
+#[derive(Default, Debug, Clone, Deserialize)]
 pub struct EventsConfig {
     pub http: Option<HttpConfig>,
 }
 
-#[derive(Default, Debug)]
+pub const DEFAULT_KEY_NAME: &str = "default";
+
+#[derive(Default, Debug, Clone)]
 pub struct HttpConfig {
     pub endpoint: String,
     pub api_key: Option<String>,
     pub hashed_api_key: Option<String>,
+    pub keys: Vec<ApiKey>,
+    pub signing: Option<SigningConfig>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct SigningConfig {
+    #[serde(default)]
+    pub required: bool,
+    #[serde(rename = "max-skew")]
+    #[serde(default = "default_max_skew", with = "humantime_serde")]
+    pub max_skew: Duration,
+}
+
+// Nonces are remembered for the skew window, so a long one mostly costs memory.
+pub const MAX_SIGNING_SKEW: Duration = Duration::from_secs(60 * 60);
+
+fn default_max_skew() -> Duration {
+    Duration::from_secs(300)
+}
+
+#[derive(Default, Debug, Clone)]
+pub struct ApiKey {
+    pub name: String,
+    pub key: String,
+    pub hashed_key: String,
+    pub expires: Option<DateTime<Utc>>,
+    pub topics: Vec<String>,
+    pub types: Vec<String>,
+}
+
+fn hash_key(key: &str) -> String {
+    let mut hasher = Sha256::new();
+    hasher.update(key.as_bytes());
+    format!("{:x}", hasher.finalize())
 }
 
 impl<'de> Deserialize<'de> for HttpConfig {
@@ -37,27 +184,137 @@ impl<'de> Deserialize<'de> for HttpConfig {
     where
         D: serde::Deserializer<'de>,
     {
+        #[derive(Deserialize)]
+        struct RawApiKey {
+            name: String,
+            key: String,
+            expires: Option<DateTime<Utc>>,
+            #[serde(default)]
+            topics: Vec<String>,
+            #[serde(default)]
+            types: Vec<String>,
+        }
         #[derive(Deserialize)]
         struct RawHttpConfig {
             endpoint: String,
             #[serde(rename = "api-key")]
             api_key: Option<String>,
+            #[serde(default)]
+            keys: Vec<RawApiKey>,
+            signing: Option<SigningConfig>,
         }
         let raw = RawHttpConfig::deserialize(deserializer)?;
-        let hashed_api_key = raw.api_key.as_ref().map(|key| {
-            let mut hasher = Sha256::new();
-            hasher.update(key.as_bytes());
-            format!("{:x}", hasher.finalize())
-        });
+        let hashed_api_key = raw.api_key.as_deref().map(hash_key);
+        let keys = raw
+            .keys
+            .into_iter()
+            .map(|k| ApiKey {
+                hashed_key: hash_key(&k.key),
+                name: k.name,
+                key: k.key,
+                expires: k.expires,
+                topics: k.topics,
+                types: k.types,
+            })
+            .collect();
         Ok(HttpConfig {
             endpoint: raw.endpoint,
             api_key: raw.api_key,
             hashed_api_key,
+            keys,
+            signing: raw.signing,
         })
     }
 }
 
+impl HttpConfig {
+    pub fn requires_key(&self) -> bool {
+        self.hashed_api_key.is_some() || !self.keys.is_empty()
+    }
+
+    // The single `api-key` predates named keys and acts as an unrestricted
+    // key called "default".
+    pub fn all_keys(&self) -> Vec<ApiKey> {
+        let mut keys = self.keys.clone();
+        if let Some(hashed_key) = &self.hashed_api_key {
+            keys.push(ApiKey {
+                name: DEFAULT_KEY_NAME.into(),
+                key: self.api_key.clone().unwrap_or_default(),
+                hashed_key: hashed_key.clone(),
+                ..Default::default()
+            });
+        }
+        keys
+    }
+}
+
+impl ApiKey {
+    pub fn is_expired(&self, now: DateTime<Utc>) -> bool {
+        self.expires.is_some_and(|expires| expires <= now)
+    }
+
+    pub fn allows(&self, topic: &str, kind: &str) -> bool {
+        (self.topics.is_empty() || self.topics.iter().any(|t| t == topic))
+            && (self.types.is_empty() || self.types.iter().any(|t| t == kind))
+    }
+}
+
+impl RouteGroup {
+    pub const ALL: [RouteGroup; 6] = [
+        RouteGroup::Ping,
+        RouteGroup::Notify,
+        RouteGroup::Events,
+        RouteGroup::Ingest,
+        RouteGroup::Admin,
+        RouteGroup::Metrics,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            RouteGroup::Ping => "ping",
+            RouteGroup::Notify => "notify",
+            RouteGroup::Events => "events",
+            RouteGroup::Ingest => "ingest",
+            RouteGroup::Admin => "admin",
+            RouteGroup::Metrics => "metrics",
+        }
+    }
+}
+
 impl AppConfig {
+    pub fn has_api_keys(&self) -> bool {
+        self.events
+            .as_ref()
+            .and_then(|e| e.http.as_ref())
+            .is_some_and(|http| http.requires_key())
+    }
+
+    pub fn auth_policy(&self, group: RouteGroup) -> AuthPolicy {
+        let configured = match group {
+            RouteGroup::Ping => &self.auth.ping,
+            RouteGroup::Notify => &self.auth.notify,
+            RouteGroup::Events => &self.auth.events,
+            RouteGroup::Ingest => &self.auth.ingest,
+            RouteGroup::Admin => &self.auth.admin,
+            RouteGroup::Metrics => &self.auth.metrics,
+        };
+        if let Some(policy) = configured {
+            return policy.clone();
+        }
+        match group {
+            RouteGroup::Ping => AuthPolicy::Public,
+            // Metrics name the keys, so they are only public without any.
+            RouteGroup::Metrics if self.has_api_keys() => AuthPolicy::ApiKey,
+            RouteGroup::Metrics => AuthPolicy::Public,
+            // Without any key configured ingest has always been open.
+            RouteGroup::Ingest if self.has_api_keys() => AuthPolicy::ApiKey,
+            RouteGroup::Ingest => AuthPolicy::Public,
+            RouteGroup::Admin => AuthPolicy::Role("admin".into()),
+            RouteGroup::Notify | RouteGroup::Events if self.auth_required => AuthPolicy::Jwt,
+            RouteGroup::Notify | RouteGroup::Events => AuthPolicy::Public,
+        }
+    }
+
     pub fn validate(&self) -> Result<(), String> {
         if let Some(events) = &self.events {
             events.validate()?;
@@ -65,14 +322,55 @@ impl AppConfig {
         if self.event_logging && self.events_logfile.is_none() {
             return Err("app: event-logging is enabled but no log file is given".into());
         }
-        if let Some(size) = self.event_segment_size {
-            if size == 0 {
-                return Err("app: event-segment-size must be greater than 0".into());
-            }
+        if let Some(size) = self.event_segment_size
+            && size == 0
+        {
+            return Err("app: event-segment-size must be greater than 0".into());
+        }
+        if let Some(max) = self.event_max_segments
+            && max == 0
+        {
+            return Err("app: event-max-segments must be greater than 0".into());
+        }
+        if let Some(capacity) = self.event_channel_capacity
+            && capacity == 0
+        {
+            return Err("app: event-channel-capacity must be greater than 0".into());
+        }
+        if let Some(retry) = self.sse_retry
+            && retry.is_zero()
+        {
+            return Err("app: sse-retry must be greater than 0".into());
+        }
+        if let Some(wal) = &self.wal {
+            wal.validate()?;
         }
-        if let Some(max) = self.event_max_segments {
-            if max == 0 {
-                return Err("app: event-max-segments must be greater than 0".into());
+        if let Some(path) = &self.snapshot_file
+            && path.trim().is_empty()
+        {
+            return Err("app: snapshot-file cannot be empty".into());
+        }
+        if let Some(presence) = &self.presence
+            && presence.admin_role.trim().is_empty()
+        {
+            return Err("app.presence.admin-role cannot be empty".into());
+        }
+        if let Some(inbox) = &self.inbox {
+            inbox.validate()?;
+        }
+        let has_keys = self.has_api_keys();
+        for group in RouteGroup::ALL {
+            match self.auth_policy(group) {
+                AuthPolicy::Role(role) if role.trim().is_empty() => {
+                    return Err(format!("app.auth.{}: role cannot be empty", group.name()));
+                }
+                AuthPolicy::ApiKey if !has_keys => {
+                    return Err(format!(
+                        "app.auth.{}: api-key requires an events.http api-key or keys",
+                        group.name()
+                    ));
+                }
+                _ => {}
             }
         }
         Ok(())
@@ -108,9 +406,85 @@ impl HttpConfig {
         if self.api_key.is_some() && self.hashed_api_key.is_none() {
             return Err("events.http.api-key was provided but hashing failed".into());
         }
-        if let Some(api_key) = &self.api_key {
-            if api_key.len() < 16 {
-                return Err("events.http.api-key must be at least 16 characters".into());
+        if let Some(api_key) = &self.api_key
+            && api_key.len() < 16
+        {
+            return Err("events.http.api-key must be at least 16 characters".into());
+        }
+        let mut names = std::collections::HashSet::new();
+        let mut hashes = std::collections::HashSet::new();
+        if self.api_key.is_some() {
+            names.insert(DEFAULT_KEY_NAME);
+        }
+        if let Some(hashed) = &self.hashed_api_key {
+            hashes.insert(hashed.as_str());
+        }
+        for key in &self.keys {
+            if key.name.trim().is_empty() {
+                return Err("events.http.keys: name cannot be empty".into());
+            }
+            if !names.insert(key.name.as_str()) {
+                return Err(format!(
+                    "events.http.keys: duplicate key name '{}'",
+                    key.name
+                ));
+            }
+            if key.key.len() < 16 {
+                return Err(format!(
+                    "events.http.keys: key '{}' must be at least 16 characters",
+                    key.name
+                ));
+            }
+            if !hashes.insert(key.hashed_key.as_str()) {
+                return Err(format!(
+                    "events.http.keys: key '{}' reuses another key's secret",
+                    key.name
+                ));
+            }
+        }
+        if let Some(signing) = &self.signing {
+            if !self.requires_key() {
+                return Err("events.http.signing requires an api-key or at least one key".into());
+            }
+            if signing.max_skew.is_zero() {
+                return Err("events.http.signing.max-skew must be greater than 0".into());
+            }
+            if signing.max_skew > MAX_SIGNING_SKEW {
+                return Err("events.http.signing.max-skew must be at most 1h".into());
+            }
+        }
+        Ok(())
+    }
+}
+
+impl InboxConfig {
+    pub fn validate(&self) -> Result<(), String> {
+        if self.max_entries == 0 {
+            return Err("app.inbox.max-entries must be greater than 0".into());
+        }
+        if self.store == InboxStoreKind::File
+            && self.path.as_deref().is_none_or(|p| p.trim().is_empty())
+        {
+            return Err("app.inbox.path must be set when store = \"file\"".into());
+        }
+        Ok(())
+    }
+}
+
+impl WalConfig {
+    pub fn validate(&self) -> Result<(), String> {
+        if self.dir.trim().is_empty() {
+            return Err("app.wal.dir cannot be empty".into());
+        }
+        if self.sync == WalSync::Interval {
+            match self.sync_interval {
+                Some(interval) if !interval.is_zero() => {}
+                _ => {
+                    return Err(
+                        "app.wal.sync-interval must be set and greater than 0 when sync = \"interval\""
+                            .into(),
+                    );
+                }
             }
         }
         Ok(())
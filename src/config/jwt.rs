@@ -0,0 +1,88 @@
+use jsonwebtoken::{
+    Algorithm, DecodingKey,
+    jwk::{JwkSet, PublicKeyUse},
+};
+use serde::Deserialize;
+use std::str::FromStr;
+use tokio::fs;
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct JwtKeyFile {
+    pub kid: Option<String>,
+    pub algorithm: Algorithm,
+    pub path: String,
+}
+
+#[derive(Debug, Clone)]
+pub struct JwtKey {
+    pub kid: Option<String>,
+    pub algorithms: Vec<Algorithm>,
+    pub key: DecodingKey,
+}
+
+pub fn is_hmac(alg: &Algorithm) -> bool {
+    matches!(alg, Algorithm::HS256 | Algorithm::HS384 | Algorithm::HS512)
+}
+
+fn pem_key(algorithm: Algorithm, pem: &[u8]) -> jsonwebtoken::errors::Result<DecodingKey> {
+    match algorithm {
+        Algorithm::ES256 | Algorithm::ES384 => DecodingKey::from_ec_pem(pem),
+        Algorithm::EdDSA => DecodingKey::from_ed_pem(pem),
+        _ => DecodingKey::from_rsa_pem(pem),
+    }
+}
+
+// A JWK without `alg` may verify any algorithm of its key type.
+fn jwk_keys(set: JwkSet) -> Result<Vec<JwtKey>, String> {
+    let mut keys = Vec::new();
+    for jwk in set.keys {
+        if matches!(jwk.common.public_key_use, Some(PublicKeyUse::Encryption)) {
+            continue;
+        }
+        let key = DecodingKey::from_jwk(&jwk).map_err(|e| {
+            format!(
+                "jwks-file: invalid key {}: {}",
+                jwk.common.key_id.as_deref().unwrap_or("without kid"),
+                e
+            )
+        })?;
+        let algorithms = match jwk.common.key_algorithm {
+            Some(alg) => match Algorithm::from_str(&alg.to_string()) {
+                Ok(alg) => vec![alg],
+                Err(_) => continue,
+            },
+            None => key.family().algorithms().to_vec(),
+        };
+        keys.push(JwtKey {
+            kid: jwk.common.key_id,
+            algorithms,
+            key,
+        });
+    }
+    Ok(keys)
+}
+
+pub async fn load_keys(files: &[JwtKeyFile], jwks: Option<&str>) -> Result<Vec<JwtKey>, String> {
+    let mut keys = Vec::new();
+    for file in files {
+        let pem = fs::read(&file.path)
+            .await
+            .map_err(|e| format!("jwt-keys: failed to read {}: {}", file.path, e))?;
+        let key = pem_key(file.algorithm, &pem)
+            .map_err(|e| format!("jwt-keys: invalid key in {}: {}", file.path, e))?;
+        keys.push(JwtKey {
+            kid: file.kid.clone(),
+            algorithms: vec![file.algorithm],
+            key,
+        });
+    }
+    if let Some(path) = jwks {
+        let contents = fs::read_to_string(path)
+            .await
+            .map_err(|e| format!("jwks-file: failed to read {}: {}", path, e))?;
+        let set: JwkSet = serde_json::from_str(&contents)
+            .map_err(|e| format!("jwks-file: invalid key set in {}: {}", path, e))?;
+        keys.extend(jwk_keys(set)?);
+    }
+    Ok(keys)
+}
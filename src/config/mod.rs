@@ -1,4 +1,6 @@
 pub mod app;
+pub mod jwt;
+pub mod reload;
 pub mod server;
 
 #[derive(Default, Debug, serde::Deserialize)]
@@ -10,8 +12,9 @@ pub struct Config {
 impl Config {
     pub async fn from_file(file_path: &str) -> Result<Config, Box<dyn std::error::Error>> {
         let contents = tokio::fs::read_to_string(file_path).await?;
-        let cfg: Config = toml::from_str(&contents)?;
+        let mut cfg: Config = toml::from_str(&contents)?;
         cfg.validate()?;
+        cfg.server.security.load_keys().await?;
         Ok(cfg)
     }
 
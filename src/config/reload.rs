@@ -0,0 +1,282 @@
+use crate::{
+    config::Config,
+    values::config::{get_config, set_config},
+};
+use std::{error::Error, time::SystemTime};
+use tokio::fs;
+
+fn keep<T: PartialEq + Clone>(
+    name: &'static str,
+    running: &T,
+    next: &mut T,
+    pending: &mut Vec<&'static str>,
+) {
+    if running != next {
+        *next = running.clone();
+        pending.push(name);
+    }
+}
+
+// Settings that are only read while the server, the buffer or the WAL start up
+// keep their running values; the ones that changed are returned so they can be
+// reported.
+fn keep_restart_only(running: &Config, next: &mut Config) -> Vec<&'static str> {
+    let mut pending = Vec::new();
+    keep(
+        "server.host",
+        &running.server.host,
+        &mut next.server.host,
+        &mut pending,
+    );
+    keep(
+        "server.port",
+        &running.server.port,
+        &mut next.server.port,
+        &mut pending,
+    );
+    keep(
+        "server.production",
+        &running.server.production,
+        &mut next.server.production,
+        &mut pending,
+    );
+    keep(
+        "server.config-poll-interval",
+        &running.server.config_poll_interval,
+        &mut next.server.config_poll_interval,
+        &mut pending,
+    );
+    keep(
+        "app.event-segment-size",
+        &running.app.event_segment_size,
+        &mut next.app.event_segment_size,
+        &mut pending,
+    );
+    keep(
+        "app.event-max-segments",
+        &running.app.event_max_segments,
+        &mut next.app.event_max_segments,
+        &mut pending,
+    );
+    keep(
+        "app.event-channel-capacity",
+        &running.app.event_channel_capacity,
+        &mut next.app.event_channel_capacity,
+        &mut pending,
+    );
+    // The event buffer picks its log when it is created.
+    keep(
+        "app.event-logging",
+        &running.app.event_logging,
+        &mut next.app.event_logging,
+        &mut pending,
+    );
+    keep(
+        "app.event-log-file",
+        &running.app.events_logfile,
+        &mut next.app.events_logfile,
+        &mut pending,
+    );
+    keep("app.wal", &running.app.wal, &mut next.app.wal, &mut pending);
+    keep(
+        "app.inbox",
+        &running.app.inbox,
+        &mut next.app.inbox,
+        &mut pending,
+    );
+    let running_http = running.app.events.as_ref().and_then(|e| e.http.as_ref());
+    let next_has_http = next.app.events.as_ref().is_some_and(|e| e.http.is_some());
+    if running_http.is_some() != next_has_http {
+        next.app.events = running.app.events.clone();
+        pending.push("app.events.http");
+    } else if let Some(running_http) = running_http
+        && let Some(next_http) = next.app.events.as_mut().and_then(|e| e.http.as_mut())
+    {
+        keep(
+            "app.events.http.endpoint",
+            &running_http.endpoint,
+            &mut next_http.endpoint,
+            &mut pending,
+        );
+    }
+    pending
+}
+
+pub async fn reload_config(path: &str) -> Result<Vec<&'static str>, Box<dyn Error>> {
+    let mut next = Config::from_file(path).await?;
+    let pending = keep_restart_only(&get_config(), &mut next);
+    next.validate()?;
+    set_config(next);
+    Ok(pending)
+}
+
+async fn reload(path: &str) {
+    match reload_config(path).await {
+        Ok(pending) if pending.is_empty() => println!("Reloaded config from {}", path),
+        Ok(pending) => println!(
+            "Reloaded config from {}; restart required to apply: {}",
+            path,
+            pending.join(", ")
+        ),
+        Err(e) => eprintln!(
+            "Failed to reload config from {}, keeping the running config: {}",
+            path, e
+        ),
+    }
+}
+
+async fn modified(path: &str) -> Option<SystemTime> {
+    fs::metadata(path).await.ok()?.modified().ok()
+}
+
+// Reloads on SIGHUP and, with `config-poll-interval`, whenever the file's
+// modification time changes.
+pub fn watch_config(path: String) {
+    #[cfg(unix)]
+    {
+        let path = path.clone();
+        tokio::spawn(async move {
+            use tokio::signal::unix::{SignalKind, signal};
+            let mut hangup = match signal(SignalKind::hangup()) {
+                Ok(s) => s,
+                Err(e) => {
+                    eprintln!("Failed to listen for SIGHUP: {}", e);
+                    return;
+                }
+            };
+            while hangup.recv().await.is_some() {
+                reload(&path).await;
+            }
+        });
+    }
+    if let Some(interval) = get_config().server.config_poll_interval {
+        tokio::spawn(async move {
+            let mut last = modified(&path).await;
+            loop {
+                tokio::time::sleep(interval).await;
+                let current = modified(&path).await;
+                if current.is_some() && current != last {
+                    last = current;
+                    reload(&path).await;
+                }
+            }
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serial_test::serial;
+
+    const CONFIG: &str = r#"
+[server]
+host = "127.0.0.1"
+port = 8080
+production = false
+cors-url = ["http://localhost:3000"]
+
+[server.security]
+jwt-secret = "first-secret"
+
+[app]
+auth-required = false
+event-logging = false
+event-log-rotation = "8h"
+event-segment-size = 100
+
+[app.events.http]
+endpoint = "/ingest"
+api-key = "first-ingest-key-0001"
+"#;
+
+    async fn write_config(contents: &str) -> String {
+        let path = std::env::temp_dir()
+            .join(format!("rodan-reload-{}.toml", rand::random::<u64>()))
+            .to_string_lossy()
+            .into_owned();
+        fs::write(&path, contents).await.unwrap();
+        path
+    }
+
+    #[tokio::test]
+    #[serial]
+    async fn test_reload_applies_reloadable_settings() {
+        let path = write_config(CONFIG).await;
+        set_config(Config::from_file(&path).await.unwrap());
+        let updated = CONFIG
+            .replace("first-secret", "second-secret")
+            .replace("first-ingest-key-0001", "second-ingest-key-002")
+            .replace("http://localhost:3000", "https://ctf.example.com");
+        fs::write(&path, updated).await.unwrap();
+        let pending = reload_config(&path).await.unwrap();
+        assert!(pending.is_empty(), "{:?}", pending);
+        let cfg = get_config();
+        assert_eq!(cfg.server.security.jwt_secret, "second-secret");
+        assert_eq!(cfg.server.cors_url, vec!["https://ctf.example.com"]);
+        assert_eq!(
+            cfg.app
+                .events
+                .as_ref()
+                .unwrap()
+                .http
+                .as_ref()
+                .unwrap()
+                .api_key,
+            Some("second-ingest-key-002".into())
+        );
+        fs::remove_file(&path).await.unwrap();
+    }
+
+    #[tokio::test]
+    #[serial]
+    async fn test_reload_keeps_restart_only_settings() {
+        let path = write_config(CONFIG).await;
+        set_config(Config::from_file(&path).await.unwrap());
+        let updated = CONFIG
+            .replace("port = 8080", "port = 9090")
+            .replace("event-segment-size = 100", "event-segment-size = 5")
+            .replace(
+                "event-logging = false",
+                "event-logging = true\nevent-log-file = \"events.log\"",
+            )
+            .replace("/ingest", "/ingest/v2")
+            .replace("first-ingest-key-0001", "second-ingest-key-002");
+        fs::write(&path, updated).await.unwrap();
+        let pending = reload_config(&path).await.unwrap();
+        assert_eq!(
+            pending,
+            vec![
+                "server.port",
+                "app.event-segment-size",
+                "app.event-logging",
+                "app.event-log-file",
+                "app.events.http.endpoint"
+            ]
+        );
+        let cfg = get_config();
+        assert_eq!(cfg.server.port, 8080);
+        assert_eq!(cfg.app.event_segment_size, Some(100));
+        assert!(!cfg.app.event_logging);
+        assert_eq!(cfg.app.events_logfile, None);
+        let http = cfg.app.events.as_ref().unwrap().http.as_ref().unwrap();
+        assert_eq!(http.endpoint, "/ingest");
+        assert_eq!(http.api_key, Some("second-ingest-key-002".into()));
+        fs::remove_file(&path).await.unwrap();
+    }
+
+    #[tokio::test]
+    #[serial]
+    async fn test_invalid_reload_keeps_running_config() {
+        let path = write_config(CONFIG).await;
+        set_config(Config::from_file(&path).await.unwrap());
+        fs::write(&path, CONFIG.replace("first-secret", "short"))
+            .await
+            .unwrap();
+        assert!(reload_config(&path).await.is_err());
+        fs::write(&path, "not = [toml").await.unwrap();
+        assert!(reload_config(&path).await.is_err());
+        assert_eq!(get_config().server.security.jwt_secret, "first-secret");
+        fs::remove_file(&path).await.unwrap();
+    }
+}
@@ -1,4 +1,7 @@
+use crate::config::jwt::{self, JwtKey, JwtKeyFile, is_hmac};
+use jsonwebtoken::{Algorithm, DecodingKey};
 use serde::Deserialize;
+use std::time::Duration;
 
 #[derive(Default, Debug, Deserialize)]
 pub struct ServerConfig {
@@ -8,12 +11,40 @@ pub struct ServerConfig {
     #[serde(rename = "cors-url")]
     pub cors_url: Vec<String>,
     pub security: self::SecurityConfig,
+    #[serde(rename = "config-poll-interval")]
+    #[serde(default, with = "humantime_serde")]
+    pub config_poll_interval: Option<Duration>,
+    #[serde(rename = "drain-period")]
+    #[serde(default, with = "humantime_serde")]
+    pub drain_period: Option<Duration>,
 }
 
 #[derive(Default, Debug, Deserialize)]
 pub struct SecurityConfig {
-    #[serde(rename = "jwt-secret")]
+    #[serde(rename = "jwt-secret", default)]
     pub jwt_secret: String,
+    #[serde(rename = "jwt-algorithms", default)]
+    pub jwt_algorithms: Vec<Algorithm>,
+    #[serde(rename = "jwt-keys", default)]
+    pub jwt_keys: Vec<JwtKeyFile>,
+    #[serde(rename = "jwks-file")]
+    pub jwks_file: Option<String>,
+    #[serde(rename = "jwt-audience", default)]
+    pub jwt_audience: Vec<String>,
+    #[serde(rename = "jwt-leeway")]
+    #[serde(default, with = "humantime_serde")]
+    pub jwt_leeway: Option<Duration>,
+    #[serde(skip)]
+    pub public_keys: Vec<JwtKey>,
+    #[serde(rename = "token-cookie")]
+    pub token_cookie: Option<String>,
+    #[serde(rename = "query-token", default)]
+    pub query_token: bool,
+    #[serde(rename = "ticket-ttl")]
+    #[serde(default, with = "humantime_serde")]
+    pub ticket_ttl: Option<Duration>,
+    #[serde(rename = "role-claim")]
+    pub role_claim: Option<String>,
 }
 
 impl ServerConfig {
@@ -30,18 +61,85 @@ impl ServerConfig {
         if self.production && self.cors_url.len() == 1 && self.cors_url[0] == "*" {
             return Err("server.cors-url cannot be '*' in production".into());
         }
+        if let Some(interval) = self.config_poll_interval
+            && interval.is_zero()
+        {
+            return Err("server.config-poll-interval must be greater than 0".into());
+        }
         self.security.validate()?;
         Ok(())
     }
 }
 
 impl SecurityConfig {
+    pub fn algorithms(&self) -> &[Algorithm] {
+        if self.jwt_algorithms.is_empty() {
+            &[Algorithm::HS256]
+        } else {
+            &self.jwt_algorithms
+        }
+    }
+
+    pub fn role_claim(&self) -> &str {
+        self.role_claim.as_deref().unwrap_or("role")
+    }
+
+    pub async fn load_keys(&mut self) -> Result<(), String> {
+        self.public_keys = jwt::load_keys(&self.jwt_keys, self.jwks_file.as_deref()).await?;
+        Ok(())
+    }
+
+    // Keys are matched on the token's `kid`; a token without one is tried
+    // against every key for its algorithm.
+    pub fn public_keys_for(&self, kid: Option<&str>, alg: Algorithm) -> Vec<&DecodingKey> {
+        self.public_keys
+            .iter()
+            .filter(|k| k.algorithms.contains(&alg))
+            .filter(|k| kid.is_none() || k.kid.as_deref() == kid)
+            .map(|k| &k.key)
+            .collect()
+    }
+
     pub fn validate(&self) -> Result<(), String> {
-        if self.jwt_secret.trim().is_empty() {
-            return Err("server.security.jwt-secret cannot be empty".into());
+        if self.algorithms().iter().any(is_hmac) {
+            if self.jwt_secret.trim().is_empty() {
+                return Err("server.security.jwt-secret cannot be empty".into());
+            }
+            if self.jwt_secret.len() < 8 {
+                return Err("server.security.jwt-secret must be at least 8 characters".into());
+            }
+        }
+        if self.algorithms().iter().any(|alg| !is_hmac(alg))
+            && self.jwt_keys.is_empty()
+            && self.jwks_file.is_none()
+        {
+            return Err(
+                "server.security: jwt-keys or jwks-file is required for asymmetric algorithms"
+                    .into(),
+            );
+        }
+        for key in &self.jwt_keys {
+            if is_hmac(&key.algorithm) || !self.algorithms().contains(&key.algorithm) {
+                return Err(format!(
+                    "server.security.jwt-keys: {:?} is not an allowed asymmetric algorithm for {}",
+                    key.algorithm, key.path
+                ));
+            }
+        }
+        if let Some(cookie) = &self.token_cookie
+            && cookie.trim().is_empty()
+        {
+            return Err("server.security.token-cookie cannot be empty".into());
+        }
+        if let Some(ttl) = self.ticket_ttl
+            && (ttl < Duration::from_secs(1) || ttl > Duration::from_secs(300))
+        {
+            return Err("server.security.ticket-ttl must be between 1s and 5m".into());
         }
-        if self.jwt_secret.len() < 8 {
-            return Err("server.security.jwt-secret must be at least 8 characters".into());
+        if let Some(claim) = &self.role_claim
+            && claim.split('.').any(|part| part.trim().is_empty())
+        {
+            return Err("server.security.role-claim is not a valid claim name".into());
         }
         Ok(())
     }
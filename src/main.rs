@@ -1,6 +1,6 @@
 use actix_web::{App, HttpServer};
 use env_logger::Env;
-use rodan_sse::{config, router::create_app, utils, values};
+use rodan_sse::{config, router::create_app, utils, utils::health::Lifecycle, values};
 use std::time::Duration;
 
 #[actix_web::main]
@@ -13,28 +13,73 @@ async fn main() -> std::io::Result<()> {
     let host: String = cfg.server.host.clone();
     let port: u32 = cfg.server.port;
     let addr: String = format!("{}:{}", host, port);
-    if cfg.app.event_logging && cfg.app.events_logfile.is_some() {
-        let rotate_duration = cfg
-            .app
-            .event_log_rotation
-            .unwrap_or_else(|| Duration::from_secs(60 * 60 * 12));
-        tokio::spawn(async move {
-            loop {
-                tokio::time::sleep(rotate_duration).await;
+    values::config::set_config(cfg);
+    utils::health::set_lifecycle(Lifecycle::Starting);
+    config::reload::watch_config(cfg_file);
+    // Rotation settings are re-read on every pass so a config reload applies.
+    tokio::spawn(async move {
+        loop {
+            let rotate_duration = values::config::get_config()
+                .app
+                .event_log_rotation
+                .unwrap_or_else(|| Duration::from_secs(60 * 60 * 12));
+            tokio::time::sleep(rotate_duration).await;
+            let cfg = values::config::get_config();
+            if cfg.app.event_logging && cfg.app.events_logfile.is_some() {
                 utils::rotate_logs().await;
             }
-        });
-    }
-    values::config::set_config(cfg);
+        }
+    });
+    // Listen before restoring so probes can see the service starting.
     let server = HttpServer::new(|| App::new().configure(create_app))
         .bind(addr)?
+        .disable_signals()
         .run();
-    tokio::select! {
-        res = server => res,
-        _ = tokio::signal::ctrl_c() => {
-            println!("Flushing logs....!");
-            utils::events::flush_events().await;
-            Ok(())
+    let handle = server.handle();
+    utils::events::restore_snapshot().await;
+    utils::events::init_wal()
+        .await
+        .expect("Failed to open the event WAL");
+    utils::inbox::init_inbox()
+        .await
+        .expect("Failed to open the inbox");
+    utils::health::set_lifecycle(Lifecycle::Ready);
+    actix_web::rt::spawn(async move {
+        shutdown_signal().await;
+        // Keep serving while load balancers see /readyz fail, then close the
+        // streams so clients reconnect elsewhere with Last-Event-ID.
+        utils::health::set_lifecycle(Lifecycle::Draining);
+        let drain = values::config::get_config()
+            .server
+            .drain_period
+            .unwrap_or(DEFAULT_DRAIN_PERIOD);
+        println!("Draining for {:?}....!", drain);
+        tokio::time::sleep(drain).await;
+        utils::connections::disconnect_matching(None, None);
+        handle.stop(true).await;
+    });
+    let res = server.await;
+    println!("Flushing logs....!");
+    utils::events::shutdown_events().await;
+    res
+}
+
+const DEFAULT_DRAIN_PERIOD: Duration = Duration::from_secs(5);
+
+async fn shutdown_signal() {
+    #[cfg(unix)]
+    {
+        use tokio::signal::unix::{SignalKind, signal};
+        match signal(SignalKind::terminate()) {
+            Ok(mut terminate) => {
+                tokio::select! {
+                    _ = tokio::signal::ctrl_c() => {}
+                    _ = terminate.recv() => {}
+                }
+                return;
+            }
+            Err(e) => eprintln!("Failed to listen for SIGTERM: {}", e),
         }
     }
+    let _ = tokio::signal::ctrl_c().await;
 }
@@ -14,6 +14,13 @@ pub fn unauthorized(req: ServiceRequest, msg: &str) -> ServiceResponse<BoxBody>
     req.into_response(resp.map_into_boxed_body())
 }
 
+pub fn forbidden(req: ServiceRequest, msg: &str) -> ServiceResponse<BoxBody> {
+    let resp = HttpResponse::Forbidden().json(ErrorResponse {
+        error: msg.to_string(),
+    });
+    req.into_response(resp.map_into_boxed_body())
+}
+
 pub async fn ping_response() -> impl Responder {
     HttpResponse::Ok().json(PingResponse {
         msg: "pong".to_string(),
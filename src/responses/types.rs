@@ -1,6 +1,14 @@
 use chrono::{DateTime, Utc};
+use std::collections::BTreeMap;
 
-use crate::utils::events::Event;
+use crate::utils::{
+    connections::Connection,
+    events::{Event, Notification},
+    health::{Checks, Lifecycle},
+    inbox::InboxEntry,
+    receipts::Unread,
+    revocation::Subject,
+};
 
 #[derive(serde::Serialize)]
 pub struct ErrorResponse {
@@ -14,15 +22,149 @@ pub struct PingResponse {
 
 #[derive(serde::Serialize)]
 pub struct EventResponse {
+    pub id: u64,
     pub timestamp: DateTime<Utc>,
-    pub message: String,
+    pub topic: String,
+    pub message: Notification,
 }
 
 impl From<Event> for EventResponse {
     fn from(event: Event) -> Self {
         EventResponse {
+            id: event.id,
             timestamp: event.timestamp,
+            topic: event.topic,
             message: event.payload,
         }
     }
 }
+
+// Key names are only shown to organizers.
+#[derive(serde::Serialize)]
+pub struct AdminEventResponse {
+    #[serde(flatten)]
+    pub event: EventResponse,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub source_key: Option<String>,
+}
+
+impl From<Event> for AdminEventResponse {
+    fn from(mut event: Event) -> Self {
+        AdminEventResponse {
+            source_key: event.source_key.take(),
+            event: EventResponse::from(event),
+        }
+    }
+}
+
+#[derive(serde::Serialize)]
+pub struct HistoryResponse {
+    pub events: Vec<EventResponse>,
+    pub next_offset: Option<usize>,
+}
+
+#[derive(serde::Serialize)]
+pub struct TicketResponse {
+    pub ticket: String,
+    pub expires_in: u64,
+}
+
+#[derive(serde::Serialize)]
+pub struct RevocationResponse {
+    #[serde(flatten)]
+    pub subject: Subject,
+    pub expires: DateTime<Utc>,
+}
+
+#[derive(serde::Serialize)]
+pub struct HealthResponse {
+    pub status: &'static str,
+    pub state: Lifecycle,
+    pub checks: Checks,
+}
+
+#[derive(serde::Serialize)]
+pub struct ConnectionResponse {
+    pub id: u64,
+    pub user_id: Option<u64>,
+    pub username: Option<String>,
+    pub team_id: Option<u64>,
+    pub ip: Option<String>,
+    pub user_agent: Option<String>,
+    pub connected: DateTime<Utc>,
+    pub delivered: u64,
+    pub last_heartbeat: Option<DateTime<Utc>>,
+}
+
+impl From<&Connection> for ConnectionResponse {
+    fn from(connection: &Connection) -> Self {
+        ConnectionResponse {
+            id: connection.id,
+            user_id: connection.user_id(),
+            username: connection.claims.as_ref().map(|c| c.username.clone()),
+            team_id: connection.team_id(),
+            ip: connection.ip.clone(),
+            user_agent: connection.user_agent.clone(),
+            connected: connection.connected,
+            delivered: connection.delivered(),
+            last_heartbeat: connection.last_heartbeat(),
+        }
+    }
+}
+
+#[derive(serde::Serialize)]
+pub struct DisconnectResponse {
+    pub disconnected: usize,
+}
+
+#[derive(serde::Serialize)]
+pub struct PresenceResponse {
+    pub team_id: u64,
+    pub online: usize,
+}
+
+#[derive(serde::Serialize)]
+pub struct UnreadResponse {
+    pub cursor: u64,
+    pub unread: usize,
+    pub topics: BTreeMap<String, usize>,
+}
+
+impl From<Unread> for UnreadResponse {
+    fn from(unread: Unread) -> Self {
+        UnreadResponse {
+            cursor: unread.cursor,
+            unread: unread.total,
+            topics: unread.topics,
+        }
+    }
+}
+
+#[derive(serde::Serialize)]
+pub struct InboxEntryResponse {
+    #[serde(flatten)]
+    pub event: EventResponse,
+    pub read: bool,
+}
+
+impl From<InboxEntry> for InboxEntryResponse {
+    fn from(entry: InboxEntry) -> Self {
+        InboxEntryResponse {
+            event: EventResponse::from(entry.event),
+            read: entry.read,
+        }
+    }
+}
+
+#[derive(serde::Serialize)]
+pub struct InboxResponse {
+    pub entries: Vec<InboxEntryResponse>,
+    pub total: usize,
+    pub unread: usize,
+    pub next_offset: Option<usize>,
+}
+
+#[derive(serde::Serialize)]
+pub struct InboxUpdateResponse {
+    pub updated: usize,
+}
@@ -0,0 +1,403 @@
+use super::{events::EventsQuery, ingester::publish};
+use crate::{
+    responses::types::{
+        AdminEventResponse, ConnectionResponse, DisconnectResponse, ErrorResponse,
+        PresenceResponse, RevocationResponse,
+    },
+    utils::{
+        connections,
+        events::{get_events, in_topics, parse_topics},
+        presence,
+        revocation::{self, Subject},
+    },
+};
+use actix_web::{HttpRequest, HttpResponse, Responder, web};
+use chrono::Utc;
+
+#[derive(serde::Deserialize)]
+pub struct ConnectionsQuery {
+    pub user_id: Option<u64>,
+    pub team_id: Option<u64>,
+}
+
+const DEFAULT_REVOCATION_SECS: u64 = 24 * 60 * 60;
+
+#[derive(serde::Deserialize)]
+pub struct RevokeRequest {
+    #[serde(flatten)]
+    pub subject: Subject,
+    pub expires_in: Option<u64>,
+}
+
+// Organizers see every buffered event, whatever its audience.
+pub async fn admin_events_handler(query: web::Query<EventsQuery>) -> impl Responder {
+    let topics = match parse_topics(query.topics.as_deref()) {
+        Ok(topics) => topics,
+        Err(e) => return HttpResponse::BadRequest().body(e),
+    };
+    let events: Vec<AdminEventResponse> = get_events(None)
+        .await
+        .into_iter()
+        .filter(|e| in_topics(e, &topics))
+        .map(AdminEventResponse::from)
+        .collect();
+    HttpResponse::Ok().json(events)
+}
+
+// Lets organizers publish alerts with their own token instead of an ingest key.
+pub async fn admin_publish_handler(req: HttpRequest, body: web::Bytes) -> impl Responder {
+    publish(&req, &body, None).await
+}
+
+pub async fn revocations_list_handler() -> impl Responder {
+    let revocations: Vec<RevocationResponse> = revocation::list()
+        .into_iter()
+        .map(|(subject, expires)| RevocationResponse { subject, expires })
+        .collect();
+    HttpResponse::Ok().json(revocations)
+}
+
+// Open streams of the subject are closed right away; new tokens and tickets
+// are refused until the revocation expires.
+pub async fn revoke_handler(body: web::Json<RevokeRequest>) -> impl Responder {
+    let body = body.into_inner();
+    let secs = body.expires_in.unwrap_or(DEFAULT_REVOCATION_SECS);
+    if secs == 0 {
+        return HttpResponse::BadRequest().json(ErrorResponse {
+            error: "expires_in must be greater than 0".into(),
+        });
+    }
+    let expires = match i64::try_from(secs)
+        .ok()
+        .and_then(chrono::Duration::try_seconds)
+        .and_then(|ttl| Utc::now().checked_add_signed(ttl))
+    {
+        Some(expires) => expires,
+        None => {
+            return HttpResponse::BadRequest().json(ErrorResponse {
+                error: "expires_in is too large".into(),
+            });
+        }
+    };
+    revocation::revoke(body.subject.clone(), expires);
+    HttpResponse::Ok().json(RevocationResponse {
+        subject: body.subject,
+        expires,
+    })
+}
+
+pub async fn lift_revocation_handler(path: web::Path<(String, String)>) -> impl Responder {
+    let (kind, id) = path.into_inner();
+    let subject = match Subject::parse(&kind, &id) {
+        Some(subject) => subject,
+        None => {
+            return HttpResponse::BadRequest().json(ErrorResponse {
+                error: format!("Invalid revocation subject '{}/{}'", kind, id),
+            });
+        }
+    };
+    if revocation::lift(&subject) {
+        HttpResponse::NoContent().finish()
+    } else {
+        HttpResponse::NotFound().json(ErrorResponse {
+            error: "No such revocation".into(),
+        })
+    }
+}
+
+pub async fn connections_list_handler(query: web::Query<ConnectionsQuery>) -> impl Responder {
+    let connections: Vec<ConnectionResponse> = connections::list(query.user_id, query.team_id)
+        .iter()
+        .map(|c| ConnectionResponse::from(c.as_ref()))
+        .collect();
+    HttpResponse::Ok().json(connections)
+}
+
+// Requires a filter so a bare DELETE cannot drop every subscriber.
+pub async fn disconnect_matching_handler(query: web::Query<ConnectionsQuery>) -> impl Responder {
+    if query.user_id.is_none() && query.team_id.is_none() {
+        return HttpResponse::BadRequest().json(ErrorResponse {
+            error: "user_id or team_id is required".into(),
+        });
+    }
+    HttpResponse::Ok().json(DisconnectResponse {
+        disconnected: connections::disconnect_matching(query.user_id, query.team_id),
+    })
+}
+
+pub async fn disconnect_handler(path: web::Path<u64>) -> impl Responder {
+    if connections::disconnect(path.into_inner()) {
+        HttpResponse::NoContent().finish()
+    } else {
+        HttpResponse::NotFound().json(ErrorResponse {
+            error: "No such connection".into(),
+        })
+    }
+}
+
+pub async fn admin_presence_handler() -> impl Responder {
+    let teams: Vec<PresenceResponse> = presence::all_online()
+        .into_iter()
+        .map(|(team_id, online)| PresenceResponse { team_id, online })
+        .collect();
+    HttpResponse::Ok().json(teams)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::router::{create_app, test_support::*};
+    use crate::values::config::set_config;
+    use actix_web::{App, body::MessageBody, http::StatusCode, test};
+    use std::pin::pin;
+
+    #[actix_web::test]
+    #[serial_test::serial]
+    async fn test_admin_role_claim() {
+        use crate::utils::health::{Lifecycle, set_lifecycle};
+        crate::utils::events::flush_events().await;
+        let mut cfg = auth_config();
+        cfg.server.security.role_claim = Some("rodan.roles".into());
+        set_config(cfg);
+        let app = test::init_service(App::new().configure(create_app)).await;
+        let now = chrono::Utc::now().timestamp();
+        let organizer = jsonwebtoken::encode(
+            &jsonwebtoken::Header::default(),
+            &serde_json::json!({
+                "user_id": 1,
+                "username": "organizer",
+                "team_id": 0,
+                "rodan": { "roles": ["admin", "judge"] },
+                "iss": "rodan",
+                "iat": now,
+                "exp": now + 600,
+            }),
+            &jsonwebtoken::EncodingKey::from_secret(b"test-secret-123"),
+        )
+        .unwrap();
+        let player = token_for(5, 3);
+        let bearer = |token: &str| ("Authorization", format!("Bearer {}", token));
+
+        let alert = serde_json::json!({ "events": [
+            { "type": "alert", "body": "Flag leaked", "audience": { "roles": ["admin"] } },
+            "Round 2 starts",
+        ]});
+        let req = test::TestRequest::post()
+            .uri("/api/admin/events")
+            .insert_header(bearer(&player))
+            .set_json(&alert)
+            .to_request();
+        assert_eq!(
+            test::call_service(&app, req).await.status(),
+            StatusCode::FORBIDDEN
+        );
+        let req = test::TestRequest::post()
+            .uri("/api/admin/events")
+            .set_json(&alert)
+            .to_request();
+        assert_eq!(
+            test::call_service(&app, req).await.status(),
+            StatusCode::UNAUTHORIZED
+        );
+        let req = test::TestRequest::post()
+            .uri("/api/admin/events")
+            .insert_header(bearer(&organizer))
+            .set_json(&alert)
+            .to_request();
+        assert_eq!(test::call_service(&app, req).await.status(), StatusCode::OK);
+        set_lifecycle(Lifecycle::Draining);
+        let req = test::TestRequest::post()
+            .uri("/api/admin/events")
+            .insert_header(bearer(&organizer))
+            .set_json(serde_json::json!({ "events": ["late"] }))
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        set_lifecycle(Lifecycle::Ready);
+        assert_eq!(resp.status(), StatusCode::SERVICE_UNAVAILABLE);
+
+        let bodies = |events: serde_json::Value| -> Vec<String> {
+            events
+                .as_array()
+                .unwrap()
+                .iter()
+                .map(|e| e["message"]["body"].as_str().unwrap().to_string())
+                .collect()
+        };
+        let req = test::TestRequest::get()
+            .uri("/api/events")
+            .insert_header(bearer(&player))
+            .to_request();
+        let events = test::call_and_read_body_json(&app, req).await;
+        assert_eq!(bodies(events), vec!["Round 2 starts"]);
+        let req = test::TestRequest::get()
+            .uri("/api/events")
+            .insert_header(bearer(&organizer))
+            .to_request();
+        let events = test::call_and_read_body_json(&app, req).await;
+        assert_eq!(bodies(events), vec!["Flag leaked", "Round 2 starts"]);
+        let req = test::TestRequest::get()
+            .uri("/api/admin/events")
+            .insert_header(bearer(&organizer))
+            .to_request();
+        let events = test::call_and_read_body_json(&app, req).await;
+        assert_eq!(bodies(events), vec!["Flag leaked", "Round 2 starts"]);
+        let req = test::TestRequest::get()
+            .uri("/api/admin/events")
+            .insert_header(bearer(&player))
+            .to_request();
+        assert_eq!(
+            test::call_service(&app, req).await.status(),
+            StatusCode::FORBIDDEN
+        );
+    }
+
+    #[actix_web::test]
+    #[serial_test::serial]
+    async fn test_revocation_closes_streams() {
+        set_config(auth_config());
+        let app = test::init_service(App::new().configure(create_app)).await;
+        let admin = ("Authorization", format!("Bearer {}", admin_token()));
+        let player = ("Authorization", format!("Bearer {}", token_for(5, 33)));
+        let notify = || {
+            test::TestRequest::get()
+                .uri("/api/notify")
+                .insert_header(player.clone())
+                .to_request()
+        };
+        let resp = test::call_service(&app, notify()).await;
+        assert_eq!(resp.status(), StatusCode::OK);
+        let mut body = pin!(resp.into_body());
+        next_chunk(&mut body).await;
+
+        let req = test::TestRequest::post()
+            .uri("/api/admin/revocations")
+            .insert_header(player.clone())
+            .set_json(serde_json::json!({ "team_id": 33 }))
+            .to_request();
+        assert_eq!(
+            test::call_service(&app, req).await.status(),
+            StatusCode::FORBIDDEN
+        );
+        let req = test::TestRequest::post()
+            .uri("/api/admin/revocations")
+            .insert_header(admin.clone())
+            .set_json(serde_json::json!({ "team_id": 33, "expires_in": 60 }))
+            .to_request();
+        let revoked: serde_json::Value = test::call_and_read_body_json(&app, req).await;
+        assert_eq!(revoked["team_id"], 33);
+
+        let chunk = next_chunk(&mut body).await;
+        assert!(
+            chunk.starts_with(b"event: revoked\n"),
+            "Expected a revoked frame, got {:?}",
+            chunk
+        );
+        let end = std::future::poll_fn(|cx| body.as_mut().poll_next(cx)).await;
+        assert!(end.is_none(), "Expected the stream to close");
+        let resp = test::call_service(&app, notify()).await;
+        assert_eq!(resp.status(), StatusCode::UNAUTHORIZED);
+        let req = test::TestRequest::post()
+            .uri("/api/notify/ticket")
+            .insert_header(player.clone())
+            .to_request();
+        assert_eq!(
+            test::call_service(&app, req).await.status(),
+            StatusCode::UNAUTHORIZED
+        );
+
+        let req = test::TestRequest::get()
+            .uri("/api/admin/revocations")
+            .insert_header(admin.clone())
+            .to_request();
+        let list: serde_json::Value = test::call_and_read_body_json(&app, req).await;
+        assert_eq!(list.as_array().unwrap().len(), 1);
+        let lift = || {
+            test::TestRequest::delete()
+                .uri("/api/admin/revocations/team/33")
+                .insert_header(admin.clone())
+                .to_request()
+        };
+        let resp = test::call_service(&app, lift()).await;
+        assert_eq!(resp.status(), StatusCode::NO_CONTENT);
+        let resp = test::call_service(&app, lift()).await;
+        assert_eq!(resp.status(), StatusCode::NOT_FOUND);
+        let resp = test::call_service(&app, notify()).await;
+        assert_eq!(resp.status(), StatusCode::OK);
+    }
+
+    #[actix_web::test]
+    #[serial_test::serial]
+    async fn test_connection_registry() {
+        use crate::utils::events::push_event;
+        set_config(auth_config());
+        let app = test::init_service(App::new().configure(create_app)).await;
+        let admin = ("Authorization", format!("Bearer {}", admin_token()));
+        let open = |user_id: u64| {
+            test::TestRequest::get()
+                .uri("/api/notify")
+                .insert_header((
+                    "Authorization",
+                    format!("Bearer {}", token_for(user_id, 61)),
+                ))
+                .insert_header(("User-Agent", "registry-test"))
+                .to_request()
+        };
+        let first = test::call_service(&app, open(601)).await;
+        let second = test::call_service(&app, open(602)).await;
+        let mut first = pin!(first.into_body());
+        let mut second = pin!(second.into_body());
+        next_chunk(&mut first).await;
+        next_chunk(&mut second).await;
+        push_event("to everyone".into()).await.unwrap();
+        next_chunk(&mut first).await;
+
+        let list = |query: &str| {
+            test::TestRequest::get()
+                .uri(&format!("/api/admin/connections{}", query))
+                .insert_header(admin.clone())
+                .to_request()
+        };
+        let listed: serde_json::Value =
+            test::call_and_read_body_json(&app, list("?team_id=61")).await;
+        assert_eq!(listed.as_array().unwrap().len(), 2);
+        let listed: serde_json::Value =
+            test::call_and_read_body_json(&app, list("?user_id=601")).await;
+        assert_eq!(listed[0]["username"], "user601");
+        assert_eq!(listed[0]["user_agent"], "registry-test");
+        assert_eq!(listed[0]["delivered"], 1);
+        let id = listed[0]["id"].as_u64().unwrap();
+
+        let req = test::TestRequest::delete()
+            .uri(&format!("/api/admin/connections/{}", id))
+            .insert_header(admin.clone())
+            .to_request();
+        assert_eq!(
+            test::call_service(&app, req).await.status(),
+            StatusCode::NO_CONTENT
+        );
+        let chunk = next_chunk(&mut first).await;
+        assert!(chunk.starts_with(b"event: disconnected\n"), "{:?}", chunk);
+        let end = std::future::poll_fn(|cx| first.as_mut().poll_next(cx)).await;
+        assert!(end.is_none(), "Expected the stream to close");
+
+        let req = test::TestRequest::delete()
+            .uri("/api/admin/connections")
+            .insert_header(admin.clone())
+            .to_request();
+        assert_eq!(
+            test::call_service(&app, req).await.status(),
+            StatusCode::BAD_REQUEST
+        );
+        let req = test::TestRequest::delete()
+            .uri("/api/admin/connections?user_id=602")
+            .insert_header(admin.clone())
+            .to_request();
+        let body: serde_json::Value = test::call_and_read_body_json(&app, req).await;
+        assert_eq!(body["disconnected"], 1);
+        // The second stream may still deliver the queued event before closing.
+        let mut chunk = next_chunk(&mut second).await;
+        if !chunk.starts_with(b"event: disconnected\n") {
+            chunk = next_chunk(&mut second).await;
+        }
+        assert!(chunk.starts_with(b"event: disconnected\n"), "{:?}", chunk);
+    }
+}
@@ -1,11 +1,61 @@
 use crate::{
-    responses::types::EventResponse,
-    utils::events::{Event, get_events},
+    responses::types::{ErrorResponse, EventResponse, HistoryResponse},
+    utils::{
+        auth::request_claims,
+        events::{Event, get_events, get_events_after, get_history, in_topics, parse_topics},
+    },
 };
-use actix_web::{HttpRequest, HttpResponse, Responder};
+use actix_web::{HttpRequest, HttpResponse, Responder, web};
 use chrono::{DateTime, Utc};
 
-pub async fn events_get_handler(req: HttpRequest) -> impl Responder {
+#[derive(serde::Deserialize)]
+pub struct EventsQuery {
+    pub topics: Option<String>,
+}
+
+const DEFAULT_HISTORY_LIMIT: usize = 100;
+const MAX_HISTORY_LIMIT: usize = 1000;
+const MAX_HISTORY_DAYS: i64 = 7;
+
+#[derive(serde::Deserialize)]
+pub struct HistoryQuery {
+    pub from: Option<DateTime<Utc>>,
+    pub to: Option<DateTime<Utc>>,
+    pub topics: Option<String>,
+    #[serde(default)]
+    pub offset: usize,
+    pub limit: Option<usize>,
+}
+
+fn visible_events(req: &HttpRequest, topics: &[String], events: Vec<Event>) -> Vec<EventResponse> {
+    let claims = request_claims(req);
+    events
+        .into_iter()
+        .filter(|e| in_topics(e, topics) && e.audience.matches(claims.as_ref()))
+        .map(EventResponse::from)
+        .collect()
+}
+
+pub async fn events_get_handler(
+    req: HttpRequest,
+    query: web::Query<EventsQuery>,
+) -> impl Responder {
+    let topics = match parse_topics(query.topics.as_deref()) {
+        Ok(topics) => topics,
+        Err(e) => return HttpResponse::BadRequest().body(e),
+    };
+    if let Some(header_value) = req.headers().get("Last-Event-ID") {
+        let last_id = match header_value
+            .to_str()
+            .ok()
+            .and_then(|v| v.trim().parse::<u64>().ok())
+        {
+            Some(id) => id,
+            None => return HttpResponse::BadRequest().body("Invalid Last-Event-ID header"),
+        };
+        let events: Vec<Event> = get_events_after(last_id).await;
+        return HttpResponse::Ok().json(visible_events(&req, &topics, events));
+    }
     let since_time: Option<DateTime<Utc>> = match req.headers().get("Last-Received-Update") {
         Some(header_value) => match header_value.to_str() {
             Ok(value_str) => match DateTime::parse_from_rfc3339(value_str) {
@@ -21,6 +71,210 @@ pub async fn events_get_handler(req: HttpRequest) -> impl Responder {
         None => None,
     };
     let events: Vec<Event> = get_events(since_time).await;
-    let response: Vec<EventResponse> = events.into_iter().map(EventResponse::from).collect();
-    HttpResponse::Ok().json(response)
+    HttpResponse::Ok().json(visible_events(&req, &topics, events))
+}
+
+pub async fn events_history_handler(
+    req: HttpRequest,
+    query: web::Query<HistoryQuery>,
+) -> impl Responder {
+    let topics = match parse_topics(query.topics.as_deref()) {
+        Ok(topics) => topics,
+        Err(e) => return HttpResponse::BadRequest().body(e),
+    };
+    // Without `from` the range starts the longest span before `to`, so a
+    // query never reads more than that span of log files.
+    let span = chrono::Duration::days(MAX_HISTORY_DAYS);
+    let to = query.to.unwrap_or_else(Utc::now);
+    let from = query.from.unwrap_or_else(|| {
+        to.checked_sub_signed(span)
+            .unwrap_or(DateTime::<Utc>::MIN_UTC)
+    });
+    if from > to {
+        return HttpResponse::BadRequest().body("from must not be after to");
+    }
+    if to - from > span {
+        return HttpResponse::BadRequest().body(format!(
+            "from and to must be at most {} days apart",
+            MAX_HISTORY_DAYS
+        ));
+    }
+    let limit = query.limit.unwrap_or(DEFAULT_HISTORY_LIMIT);
+    if limit == 0 || limit > MAX_HISTORY_LIMIT {
+        return HttpResponse::BadRequest()
+            .body(format!("limit must be between 1 and {}", MAX_HISTORY_LIMIT));
+    }
+    // One event past the page tells whether there is a next one.
+    let end = query.offset.saturating_add(limit);
+    let claims = request_claims(&req);
+    let visible = |e: &Event| in_topics(e, &topics) && e.audience.matches(claims.as_ref());
+    let events = match get_history(Some(from), query.to, visible, end.saturating_add(1)).await {
+        Ok(events) => events,
+        Err(e) => {
+            return HttpResponse::InternalServerError().json(ErrorResponse {
+                error: format!("Failed to read event history: {}", e),
+            });
+        }
+    };
+    let more = events.len() > end;
+    let page: Vec<EventResponse> = events
+        .into_iter()
+        .skip(query.offset)
+        .take(limit)
+        .map(EventResponse::from)
+        .collect();
+    HttpResponse::Ok().json(HistoryResponse {
+        events: page,
+        next_offset: more.then_some(end),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::router::{create_app, test_support::*};
+    use crate::values::config::set_config;
+    use actix_web::{App, http::StatusCode, test};
+    use std::pin::pin;
+
+    #[actix_web::test]
+    #[serial_test::serial]
+    async fn test_events_are_filtered_by_audience() {
+        use crate::utils::events::{flush_events, push_event};
+        set_config(auth_config());
+        flush_events().await;
+        push_event("for everyone".into()).await.unwrap();
+        push_event(targeted("team 1 reviewed", vec![], vec![1]))
+            .await
+            .unwrap();
+        push_event(targeted("user 9 only", vec![9], vec![]))
+            .await
+            .unwrap();
+        let app = test::init_service(App::new().configure(create_app)).await;
+
+        let bodies = |resp: Vec<serde_json::Value>| -> Vec<String> {
+            resp.iter()
+                .map(|e| e["message"]["body"].as_str().unwrap().to_string())
+                .collect()
+        };
+        let req = test::TestRequest::get()
+            .uri("/api/events")
+            .insert_header(("Authorization", format!("Bearer {}", token_for(5, 1))))
+            .to_request();
+        let resp: Vec<serde_json::Value> = test::call_and_read_body_json(&app, req).await;
+        assert_eq!(bodies(resp), vec!["for everyone", "team 1 reviewed"]);
+
+        let req = test::TestRequest::get()
+            .uri("/api/events")
+            .insert_header(("Authorization", format!("Bearer {}", token_for(9, 2))))
+            .to_request();
+        let resp: Vec<serde_json::Value> = test::call_and_read_body_json(&app, req).await;
+        assert_eq!(bodies(resp), vec!["for everyone", "user 9 only"]);
+
+        let req = test::TestRequest::get()
+            .uri("/api/notify")
+            .insert_header(("Authorization", format!("Bearer {}", token_for(5, 2))))
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), StatusCode::OK);
+        let mut body = pin!(resp.into_body());
+        next_chunk(&mut body).await;
+        push_event(targeted("team 1 only", vec![], vec![1]))
+            .await
+            .unwrap();
+        let visible = push_event("visible".into()).await.unwrap();
+        assert_eq!(next_chunk(&mut body).await, event_frame(&visible));
+    }
+
+    #[actix_web::test]
+    #[serial_test::serial]
+    async fn test_history_reads_rotated_logs() {
+        use crate::utils::events::{flush_events, push_event};
+        use chrono::{Duration, Utc};
+        let dir = std::env::temp_dir().join(format!("rodan-history-{}", rand::random::<u64>()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("events.log");
+        let mut cfg = test_config();
+        cfg.app.event_logging = true;
+        cfg.app.events_logfile = Some(path.to_string_lossy().into_owned());
+        set_config(cfg);
+        flush_events().await;
+        let now = Utc::now();
+        let line = |id: u64, body: &str, age: Duration, audience: &str| {
+            format!(
+                r#"{{"id":{},"timestamp":"{}","level":"INFO","target":"rodan.events","message":{{"type":"message","body":"{}"}},{}"topic":"default","type":"notifications"}}"#,
+                id,
+                (now - age).to_rfc3339(),
+                body,
+                audience
+            )
+        };
+        let rotated_at = (now - Duration::hours(20)).format("%Y%m%d%H%M%S");
+        std::fs::write(
+            format!("{}.{}", path.display(), rotated_at),
+            line(1, "oldest", Duration::hours(30), "") + "\n",
+        )
+        .unwrap();
+        std::fs::write(
+            &path,
+            [
+                line(2, "yesterday", Duration::hours(10), ""),
+                line(
+                    3,
+                    "team only",
+                    Duration::hours(9),
+                    r#""audience":{"teams":[7]},"#,
+                ),
+                "{}".into(),
+            ]
+            .join("\n")
+                + "\n",
+        )
+        .unwrap();
+        let live = push_event("live".into()).await.unwrap();
+        let app = test::init_service(App::new().configure(create_app)).await;
+
+        let req = test::TestRequest::get()
+            .uri("/api/events/history?limit=2")
+            .to_request();
+        let body: serde_json::Value = test::call_and_read_body_json(&app, req).await;
+        let bodies: Vec<_> = body["events"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|e| e["message"]["body"].as_str().unwrap().to_string())
+            .collect();
+        assert_eq!(bodies, vec!["oldest", "yesterday"]);
+        assert_eq!(body["next_offset"], 2);
+
+        let req = test::TestRequest::get()
+            .uri("/api/events/history?limit=2&offset=2")
+            .to_request();
+        let body: serde_json::Value = test::call_and_read_body_json(&app, req).await;
+        assert_eq!(body["events"][0]["id"], live.id);
+        assert_eq!(body["next_offset"], serde_json::Value::Null);
+
+        let from = (now - Duration::hours(12)).format("%Y-%m-%dT%H:%M:%SZ");
+        let to = (now - Duration::hours(1)).format("%Y-%m-%dT%H:%M:%SZ");
+        let req = test::TestRequest::get()
+            .uri(&format!("/api/events/history?from={}&to={}", from, to))
+            .to_request();
+        let body: serde_json::Value = test::call_and_read_body_json(&app, req).await;
+        assert_eq!(body["events"].as_array().unwrap().len(), 1);
+        assert_eq!(body["events"][0]["message"]["body"], "yesterday");
+
+        let long_ago = (now - Duration::days(30)).format("%Y-%m-%dT%H:%M:%SZ");
+        for uri in [
+            "/api/events/history?limit=0",
+            "/api/events/history?from=yesterday",
+            &format!("/api/events/history?from={}&to={}", to, from),
+            &format!("/api/events/history?from={}", long_ago),
+        ] {
+            let req = test::TestRequest::get().uri(uri).to_request();
+            let resp = test::call_service(&app, req).await;
+            assert_eq!(resp.status(), StatusCode::BAD_REQUEST, "{}", uri);
+        }
+        set_config(test_config());
+        flush_events().await;
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
 }
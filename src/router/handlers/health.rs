@@ -0,0 +1,81 @@
+use crate::{
+    responses::types::HealthResponse,
+    utils::health::{self, Checks},
+};
+use actix_web::{HttpResponse, Responder};
+
+fn health_response(checks: Checks) -> HttpResponse {
+    let body = HealthResponse {
+        status: if health::passed(&checks) {
+            "ok"
+        } else {
+            "fail"
+        },
+        state: health::lifecycle(),
+        checks,
+    };
+    if body.status == "ok" {
+        HttpResponse::Ok().json(body)
+    } else {
+        HttpResponse::ServiceUnavailable().json(body)
+    }
+}
+
+pub async fn healthz_handler() -> impl Responder {
+    health_response(health::liveness().await)
+}
+
+pub async fn readyz_handler() -> impl Responder {
+    health_response(health::readiness().await)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::router::{create_app, test_support::*};
+    use crate::values::config::set_config;
+    use actix_web::{App, http::StatusCode, test};
+
+    #[actix_web::test]
+    #[serial_test::serial]
+    async fn test_health_and_readiness() {
+        use crate::utils::health::{Lifecycle, set_lifecycle};
+        set_config(test_config());
+        let app = test::init_service(App::new().configure(create_app)).await;
+        let probe = |uri: &str| test::TestRequest::get().uri(uri).to_request();
+        let resp = test::call_service(&app, probe("/healthz")).await;
+        assert_eq!(resp.status(), StatusCode::OK);
+        let resp = test::call_service(&app, probe("/readyz")).await;
+        assert_eq!(resp.status(), StatusCode::OK);
+        let body: serde_json::Value = test::read_body_json(resp).await;
+        assert_eq!(body["state"], "ready");
+        assert_eq!(body["checks"]["buffer"]["status"], "ok");
+        assert_eq!(body["checks"]["event_log"]["status"], "skipped");
+        assert_eq!(body["checks"]["ingest"]["status"], "ok");
+
+        let mut cfg = test_config();
+        cfg.app.event_logging = true;
+        cfg.app.events_logfile = Some("/proc/rodan/events.log".into());
+        set_config(cfg);
+        let resp = test::call_service(&app, probe("/readyz")).await;
+        assert_eq!(resp.status(), StatusCode::SERVICE_UNAVAILABLE);
+        let body: serde_json::Value = test::read_body_json(resp).await;
+        assert_eq!(body["status"], "fail");
+        assert_eq!(body["checks"]["event_log"]["status"], "fail");
+        let resp = test::call_service(&app, probe("/healthz")).await;
+        assert_eq!(resp.status(), StatusCode::OK);
+
+        set_config(test_config());
+        set_lifecycle(Lifecycle::Draining);
+        let resp = test::call_service(&app, probe("/readyz")).await;
+        assert_eq!(resp.status(), StatusCode::SERVICE_UNAVAILABLE);
+        let body: serde_json::Value = test::read_body_json(resp).await;
+        assert_eq!(body["state"], "draining");
+        let req = test::TestRequest::post()
+            .uri("/api/ingest/event")
+            .set_json(serde_json::json!({ "events": ["late"] }))
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        set_lifecycle(Lifecycle::Ready);
+        assert_eq!(resp.status(), StatusCode::SERVICE_UNAVAILABLE);
+    }
+}
@@ -0,0 +1,270 @@
+use crate::{
+    responses::types::{ErrorResponse, InboxEntryResponse, InboxResponse, InboxUpdateResponse},
+    utils::{
+        auth::{Claims, request_claims},
+        events::{in_topics, parse_topics},
+        inbox::{self, EntryState, InboxEntry},
+    },
+};
+use actix_web::{HttpRequest, HttpResponse, Responder, web};
+
+const DEFAULT_INBOX_LIMIT: usize = 50;
+const MAX_INBOX_LIMIT: usize = 200;
+
+#[derive(serde::Deserialize)]
+pub struct InboxQuery {
+    pub topics: Option<String>,
+    #[serde(default)]
+    pub unread: bool,
+    #[serde(default)]
+    pub offset: usize,
+    pub limit: Option<usize>,
+}
+
+#[derive(serde::Deserialize)]
+pub struct MarkReadRequest {
+    pub ids: Vec<u64>,
+}
+
+// Entries are stored by user and team; the event's roles, if any, still have
+// to match, as they would for delivery.
+async fn visible_entries(claims: &Claims) -> Result<Vec<InboxEntry>, HttpResponse> {
+    let Some(store) = inbox::store() else {
+        return Err(HttpResponse::ServiceUnavailable().json(ErrorResponse {
+            error: "The inbox is not open yet".into(),
+        }));
+    };
+    match store.entries(claims.user_id, claims.team_id).await {
+        Ok(entries) => Ok(entries
+            .into_iter()
+            .filter(|e| e.event.audience.matches(Some(claims)))
+            .collect()),
+        Err(e) => Err(HttpResponse::InternalServerError().json(ErrorResponse {
+            error: format!("Failed to read the inbox: {}", e),
+        })),
+    }
+}
+
+// Only entries the caller can see are passed on, so ids of other users'
+// entries are ignored rather than marked.
+async fn mark(claims: &Claims, ids: Vec<u64>, state: EntryState) -> Result<usize, HttpResponse> {
+    let visible: Vec<u64> = visible_entries(claims)
+        .await?
+        .into_iter()
+        .map(|e| e.event.id)
+        .filter(|id| ids.contains(id))
+        .collect();
+    let Some(store) = inbox::store() else {
+        return Ok(0);
+    };
+    store
+        .mark(claims.user_id, visible, state)
+        .await
+        .map_err(|e| {
+            HttpResponse::InternalServerError().json(ErrorResponse {
+                error: format!("Failed to update the inbox: {}", e),
+            })
+        })
+}
+
+fn signed_in_required() -> HttpResponse {
+    HttpResponse::Unauthorized().json(ErrorResponse {
+        error: "The inbox requires a signed-in user".into(),
+    })
+}
+
+pub async fn inbox_handler(req: HttpRequest, query: web::Query<InboxQuery>) -> impl Responder {
+    let Some(claims) = request_claims(&req) else {
+        return signed_in_required();
+    };
+    let topics = match parse_topics(query.topics.as_deref()) {
+        Ok(topics) => topics,
+        Err(e) => return HttpResponse::BadRequest().body(e),
+    };
+    let limit = query.limit.unwrap_or(DEFAULT_INBOX_LIMIT);
+    if limit == 0 || limit > MAX_INBOX_LIMIT {
+        return HttpResponse::BadRequest()
+            .body(format!("limit must be between 1 and {}", MAX_INBOX_LIMIT));
+    }
+    let entries: Vec<InboxEntry> = match visible_entries(&claims).await {
+        Ok(entries) => entries
+            .into_iter()
+            .filter(|e| in_topics(&e.event, &topics))
+            .collect(),
+        Err(resp) => return resp,
+    };
+    let unread = entries.iter().filter(|e| !e.read).count();
+    let entries: Vec<InboxEntry> = entries
+        .into_iter()
+        .filter(|e| !query.unread || !e.read)
+        .collect();
+    let total = entries.len();
+    let page: Vec<InboxEntryResponse> = entries
+        .into_iter()
+        .skip(query.offset)
+        .take(limit)
+        .map(InboxEntryResponse::from)
+        .collect();
+    let end = query.offset.saturating_add(page.len());
+    HttpResponse::Ok().json(InboxResponse {
+        entries: page,
+        total,
+        unread,
+        next_offset: (end < total).then_some(end),
+    })
+}
+
+pub async fn inbox_read_handler(
+    req: HttpRequest,
+    body: web::Json<MarkReadRequest>,
+) -> impl Responder {
+    let Some(claims) = request_claims(&req) else {
+        return signed_in_required();
+    };
+    match mark(&claims, body.into_inner().ids, EntryState::Read).await {
+        Ok(updated) => HttpResponse::Ok().json(InboxUpdateResponse { updated }),
+        Err(resp) => resp,
+    }
+}
+
+pub async fn inbox_read_all_handler(req: HttpRequest) -> impl Responder {
+    let Some(claims) = request_claims(&req) else {
+        return signed_in_required();
+    };
+    let ids = match visible_entries(&claims).await {
+        Ok(entries) => entries
+            .into_iter()
+            .filter(|e| !e.read)
+            .map(|e| e.event.id)
+            .collect(),
+        Err(resp) => return resp,
+    };
+    match mark(&claims, ids, EntryState::Read).await {
+        Ok(updated) => HttpResponse::Ok().json(InboxUpdateResponse { updated }),
+        Err(resp) => resp,
+    }
+}
+
+// For a team entry this only hides it from the caller.
+pub async fn inbox_delete_handler(req: HttpRequest, path: web::Path<u64>) -> impl Responder {
+    let Some(claims) = request_claims(&req) else {
+        return signed_in_required();
+    };
+    let id = path.into_inner();
+    match mark(&claims, vec![id], EntryState::Deleted).await {
+        Ok(0) => HttpResponse::NotFound().json(ErrorResponse {
+            error: format!("No inbox entry {}", id),
+        }),
+        Ok(_) => HttpResponse::NoContent().finish(),
+        Err(resp) => resp,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::router::{create_app, test_support::*};
+    use crate::values::config::set_config;
+    use actix_web::{App, http::StatusCode, test};
+
+    #[actix_web::test]
+    #[serial_test::serial]
+    async fn test_inbox() {
+        use crate::utils::{
+            events::{EventDraft, flush_events, push_event},
+            inbox,
+        };
+        let mut cfg = auth_config();
+        cfg.app.inbox = Some(toml::from_str("store = \"memory\"").unwrap());
+        set_config(cfg);
+        inbox::init_inbox().await.unwrap();
+        let app = test::init_service(App::new().configure(create_app)).await;
+        let bearer = |user_id: u64| {
+            (
+                "Authorization",
+                format!("Bearer {}", token_for(user_id, 91)),
+            )
+        };
+        let list = |user_id: u64, query: &str| {
+            test::TestRequest::get()
+                .uri(&format!("/api/inbox{}", query))
+                .insert_header(bearer(user_id))
+                .to_request()
+        };
+
+        let reviewed = push_event(EventDraft {
+            topic: "write-ups".into(),
+            ..targeted("write-up reviewed", vec![901], vec![])
+        })
+        .await
+        .unwrap();
+        let other = push_event(targeted("someone else", vec![902], vec![]))
+            .await
+            .unwrap();
+        push_event("for everyone".into()).await.unwrap();
+        let hint = push_event(targeted("hint unlocked", vec![], vec![91]))
+            .await
+            .unwrap();
+        // Delivery happens after publishing, on the inbox's own task.
+        let store = inbox::store().unwrap();
+        while store.last_id().await.unwrap() < hint.id {
+            tokio::time::sleep(std::time::Duration::from_millis(5)).await;
+        }
+        // The inbox outlives the buffer.
+        flush_events().await;
+
+        let body: serde_json::Value = test::call_and_read_body_json(&app, list(901, "")).await;
+        assert_eq!(body["total"], 2);
+        assert_eq!(body["unread"], 2);
+        assert_eq!(body["entries"][0]["id"], hint.id);
+        assert_eq!(body["entries"][0]["read"], false);
+        assert_eq!(body["entries"][1]["message"]["body"], "write-up reviewed");
+        assert_eq!(body["entries"][1]["topic"], "write-ups");
+        let body: serde_json::Value =
+            test::call_and_read_body_json(&app, list(901, "?limit=1")).await;
+        assert_eq!(body["entries"].as_array().unwrap().len(), 1);
+        assert_eq!(body["next_offset"], 1);
+
+        let req = test::TestRequest::post()
+            .uri("/api/inbox/read")
+            .insert_header(bearer(901))
+            .set_json(serde_json::json!({ "ids": [reviewed.id, other.id] }))
+            .to_request();
+        let body: serde_json::Value = test::call_and_read_body_json(&app, req).await;
+        assert_eq!(body["updated"], 1);
+        let body: serde_json::Value =
+            test::call_and_read_body_json(&app, list(901, "?unread=true")).await;
+        assert_eq!(body["total"], 1);
+        assert_eq!(body["entries"][0]["id"], hint.id);
+
+        // Deleting a team entry only hides it from that member.
+        let delete = || {
+            test::TestRequest::delete()
+                .uri(&format!("/api/inbox/{}", hint.id))
+                .insert_header(bearer(903))
+                .to_request()
+        };
+        let resp = test::call_service(&app, delete()).await;
+        assert_eq!(resp.status(), StatusCode::NO_CONTENT);
+        let resp = test::call_service(&app, delete()).await;
+        assert_eq!(resp.status(), StatusCode::NOT_FOUND);
+        let body: serde_json::Value = test::call_and_read_body_json(&app, list(903, "")).await;
+        assert_eq!(body["total"], 0);
+
+        let req = test::TestRequest::post()
+            .uri("/api/inbox/read-all")
+            .insert_header(bearer(901))
+            .to_request();
+        let body: serde_json::Value = test::call_and_read_body_json(&app, req).await;
+        assert_eq!(body["updated"], 1);
+        let body: serde_json::Value = test::call_and_read_body_json(&app, list(901, "")).await;
+        assert_eq!(body["total"], 2);
+        assert_eq!(body["unread"], 0);
+
+        let req = test::TestRequest::get().uri("/api/inbox").to_request();
+        assert_eq!(
+            test::call_service(&app, req).await.status(),
+            StatusCode::UNAUTHORIZED
+        );
+        inbox::set_store(None);
+    }
+}
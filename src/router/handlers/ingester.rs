@@ -1,15 +1,45 @@
-use crate::{responses::types, utils::events::push_event, values::config::get_config};
-use actix_web::{HttpResponse, Responder, web};
+use crate::{
+    config::app::{ApiKey, AuthPolicy, HttpConfig, RouteGroup},
+    responses::types,
+    utils::{
+        auth::match_api_key,
+        events::{EventDraft, IncomingNotification, push_event},
+        health::{self, Lifecycle},
+        metrics,
+        presence::PRESENCE_TOPIC,
+        signing,
+    },
+    values::config::get_config,
+};
+use actix_web::{HttpMessage, HttpRequest, HttpResponse, Responder, mime, web};
+use chrono::Utc;
+
+// What `web::Json` accepted before the body was read raw for signatures; the
+// resources register it as their `PayloadConfig` limit.
+pub const MAX_BODY_SIZE: usize = 2 * 1024 * 1024;
 
 #[derive(serde::Deserialize)]
 pub struct EventsPayload {
-    pub events: Vec<String>,
+    pub events: Vec<IncomingNotification>,
+}
+
+// Signed requests are checked against the raw key. Without a signature the
+// hashed key in `x-api-key` is accepted unless signing is required.
+fn authenticate(http_cfg: &HttpConfig, req: &HttpRequest, body: &[u8]) -> Result<ApiKey, String> {
+    let now = Utc::now();
+    let key = match &http_cfg.signing {
+        Some(signing) if signing.required || signing::is_signed(req.headers()) => {
+            signing::verify(http_cfg, signing, req.headers(), body, now)?
+        }
+        _ => match_api_key(http_cfg, req.headers())?,
+    };
+    if key.is_expired(now) {
+        return Err(format!("API key '{}' has expired", key.name));
+    }
+    Ok(key)
 }
 
-pub async fn events_ingestor(
-    payload: web::Json<EventsPayload>,
-    req: actix_web::HttpRequest,
-) -> impl Responder {
+pub async fn events_ingestor(body: web::Bytes, req: HttpRequest) -> impl Responder {
     let cfg = get_config();
     let events_cfg = match &cfg.app.events {
         Some(ev) => ev,
@@ -27,21 +57,278 @@ pub async fn events_ingestor(
             });
         }
     };
-    if let Some(hashed_api_key) = &http_cfg.hashed_api_key {
-        let api_key_header = req.headers().get("x-api-key").and_then(|v| v.to_str().ok());
-        let valid = if let Some(api_key) = api_key_header {
-            api_key == hashed_api_key
-        } else {
-            false
-        };
-        if !valid {
-            return HttpResponse::Unauthorized().json(types::ErrorResponse {
-                error: "Invalid API key".into(),
+    // Under any other policy the middleware has already let the request in.
+    let api_key = if cfg.app.auth_policy(RouteGroup::Ingest) == AuthPolicy::ApiKey {
+        match authenticate(http_cfg, &req, &body) {
+            Ok(key) => Some(key),
+            Err(e) => {
+                return HttpResponse::Unauthorized().json(types::ErrorResponse { error: e });
+            }
+        }
+    } else {
+        None
+    };
+    publish(&req, &body, api_key.as_ref()).await
+}
+
+fn is_json(req: &HttpRequest) -> bool {
+    matches!(req.mime_type(), Ok(Some(mime)) if mime.type_() == mime::APPLICATION
+        && (mime.subtype() == mime::JSON || mime.suffix() == Some(mime::JSON)))
+}
+
+// Parses and stores a batch of events. A key's topic and type restrictions are
+// checked for the whole batch before anything is stored.
+pub async fn publish(req: &HttpRequest, body: &[u8], api_key: Option<&ApiKey>) -> HttpResponse {
+    // Events accepted while the buffer is restored or after it was persisted
+    // for shutdown would be lost or misordered.
+    if health::lifecycle() != Lifecycle::Ready {
+        return HttpResponse::ServiceUnavailable().json(types::ErrorResponse {
+            error: "Service is not ready".into(),
+        });
+    }
+    if !is_json(req) {
+        return HttpResponse::UnsupportedMediaType().json(types::ErrorResponse {
+            error: "Content-Type must be application/json".into(),
+        });
+    }
+    let payload: EventsPayload = match serde_json::from_slice(body) {
+        Ok(payload) => payload,
+        Err(e) => {
+            return HttpResponse::BadRequest().json(types::ErrorResponse {
+                error: format!("Invalid payload: {}", e),
             });
         }
+    };
+    let mut drafts: Vec<EventDraft> = payload.events.into_iter().map(EventDraft::from).collect();
+    for draft in drafts.iter_mut() {
+        if let Err(e) = draft.validate() {
+            return HttpResponse::BadRequest().json(types::ErrorResponse { error: e });
+        }
+        if draft.topic == PRESENCE_TOPIC {
+            return HttpResponse::BadRequest().json(types::ErrorResponse {
+                error: format!("Topic '{}' is reserved", PRESENCE_TOPIC),
+            });
+        }
+        if let Some(key) = api_key {
+            if !key.allows(&draft.topic, &draft.payload.kind) {
+                return HttpResponse::Forbidden().json(types::ErrorResponse {
+                    error: format!(
+                        "API key '{}' may not publish '{}' events to topic '{}'",
+                        key.name, draft.payload.kind, draft.topic
+                    ),
+                });
+            }
+            draft.source_key = Some(key.name.clone());
+        }
     }
-    for event in &payload.events {
-        push_event(event.clone()).await;
+    let key_name = api_key.map_or("none", |key| key.name.as_str());
+    for draft in drafts {
+        match push_event(draft).await {
+            Ok(event) => metrics::record_ingest(key_name, &event.topic),
+            Err(e) => {
+                return HttpResponse::InternalServerError().json(types::ErrorResponse {
+                    error: format!("Failed to persist event: {}", e),
+                });
+            }
+        }
     }
     HttpResponse::Ok().body("Events ingested")
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::router::{create_app, test_support::*};
+    use crate::values::config::set_config;
+    use actix_web::http::header::CONTENT_TYPE;
+    use actix_web::{App, http::StatusCode, test};
+
+    #[actix_web::test]
+    #[serial_test::serial]
+    async fn test_named_api_keys() {
+        use sha2::{Digest, Sha256};
+        let hashed = |key: &str| format!("{:x}", Sha256::digest(key.as_bytes()));
+        let mut cfg = test_config();
+        cfg.server.security.jwt_secret = "test-secret-123".into();
+        let http: HttpConfig = toml::from_str(
+            r#"
+            endpoint = "/ingest/event"
+            api-key = "legacy-key-0123456789"
+
+            [[keys]]
+            name = "rodan-core"
+            key = "core-key-0123456789"
+            topics = ["announcements"]
+            types = ["announcement"]
+
+            [[keys]]
+            name = "cron"
+            key = "cron-key-0123456789"
+            expires = "2020-01-01T00:00:00Z"
+            "#,
+        )
+        .unwrap();
+        http.validate().unwrap();
+        cfg.app.events.as_mut().unwrap().http = Some(http);
+        set_config(cfg);
+        crate::utils::events::flush_events().await;
+        let app = test::init_service(App::new().configure(create_app)).await;
+        let ingest = |key: &str, event: serde_json::Value| {
+            test::TestRequest::post()
+                .uri("/api/ingest/event")
+                .insert_header(("x-api-key", hashed(key)))
+                .set_json(serde_json::json!({ "events": [event] }))
+                .to_request()
+        };
+        let announcement = serde_json::json!({
+            "type": "announcement",
+            "body": "CTF starts",
+            "topic": "announcements",
+        });
+
+        let resp =
+            test::call_service(&app, ingest("core-key-0123456789", announcement.clone())).await;
+        assert_eq!(resp.status(), StatusCode::OK);
+        let resp = test::call_service(&app, ingest("legacy-key-0123456789", "legacy".into())).await;
+        assert_eq!(resp.status(), StatusCode::OK);
+        let resp =
+            test::call_service(&app, ingest("core-key-0123456789", "off topic".into())).await;
+        assert_eq!(resp.status(), StatusCode::FORBIDDEN);
+        let resp = test::call_service(&app, ingest("cron-key-0123456789", announcement)).await;
+        assert_eq!(resp.status(), StatusCode::UNAUTHORIZED);
+        let body: serde_json::Value = test::read_body_json(resp).await;
+        assert_eq!(body["error"], "API key 'cron' has expired");
+        let resp = test::call_service(&app, ingest("unknown-key-0123456", "nope".into())).await;
+        assert_eq!(resp.status(), StatusCode::UNAUTHORIZED);
+
+        let req = test::TestRequest::get().uri("/api/events").to_request();
+        let body: serde_json::Value = test::call_and_read_body_json(&app, req).await;
+        assert!(
+            body.as_array()
+                .unwrap()
+                .iter()
+                .all(|e| e.get("source_key").is_none())
+        );
+        let req = test::TestRequest::get()
+            .uri("/api/admin/events")
+            .insert_header(("Authorization", format!("Bearer {}", admin_token())))
+            .to_request();
+        let body: serde_json::Value = test::call_and_read_body_json(&app, req).await;
+        let sources: Vec<_> = body
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|e| e["source_key"].as_str().unwrap().to_string())
+            .collect();
+        assert_eq!(sources, vec!["rodan-core", "default"]);
+    }
+
+    #[actix_web::test]
+    #[serial_test::serial]
+    async fn test_signed_ingest() {
+        use crate::utils::signing::sign;
+        use sha2::{Digest, Sha256};
+        let mut cfg = test_config();
+        let http: HttpConfig = toml::from_str(
+            r#"
+            endpoint = "/ingest/event"
+            api-key = "legacy-key-0123456789"
+
+            [signing]
+            required = true
+            max-skew = "1m"
+            "#,
+        )
+        .unwrap();
+        http.validate().unwrap();
+        cfg.app.events.as_mut().unwrap().http = Some(http);
+        set_config(cfg);
+        let app = test::init_service(App::new().configure(create_app)).await;
+        let body = r#"{"events":["signed"]}"#;
+        let signed = |timestamp: i64, nonce: &str, signature: String| {
+            test::TestRequest::post()
+                .uri("/api/ingest/event")
+                .insert_header(("x-timestamp", timestamp.to_string()))
+                .insert_header(("x-nonce", nonce.to_string()))
+                .insert_header(("x-signature", signature))
+                .insert_header((CONTENT_TYPE, "application/json"))
+                .set_payload(body)
+                .to_request()
+        };
+        let now = chrono::Utc::now().timestamp();
+        let nonce = format!("nonce-{}", rand::random::<u64>());
+        let signature = sign(
+            "legacy-key-0123456789",
+            &now.to_string(),
+            &nonce,
+            body.as_bytes(),
+        );
+
+        let resp = test::call_service(&app, signed(now, &nonce, signature.clone())).await;
+        assert_eq!(resp.status(), StatusCode::OK);
+        let resp = test::call_service(&app, signed(now, &nonce, signature)).await;
+        assert_eq!(resp.status(), StatusCode::UNAUTHORIZED);
+        let old = now - 600;
+        let stale = sign(
+            "legacy-key-0123456789",
+            &old.to_string(),
+            "stale-nonce",
+            body.as_bytes(),
+        );
+        let resp = test::call_service(&app, signed(old, "stale-nonce", stale)).await;
+        assert_eq!(resp.status(), StatusCode::UNAUTHORIZED);
+        let req = test::TestRequest::post()
+            .uri("/api/ingest/event")
+            .insert_header((
+                "x-api-key",
+                format!("{:x}", Sha256::digest(b"legacy-key-0123456789")),
+            ))
+            .insert_header((CONTENT_TYPE, "application/json"))
+            .set_payload(body)
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), StatusCode::UNAUTHORIZED);
+        let body: serde_json::Value = test::read_body_json(resp).await;
+        assert_eq!(body["error"], "Missing x-signature header");
+    }
+
+    #[actix_web::test]
+    #[serial_test::serial]
+    async fn test_ingest_body_limit_and_content_type() {
+        set_config(test_config());
+        let app = test::init_service(App::new().configure(create_app)).await;
+        let ingest = |content_type: &str, body: String| {
+            test::TestRequest::post()
+                .uri("/api/ingest/event")
+                .insert_header((CONTENT_TYPE, content_type.to_string()))
+                .set_payload(body)
+                .to_request()
+        };
+        // Larger than actix's 256 KB default, within the 2 MB JSON limit.
+        let large = serde_json::json!({ "events": ["x".repeat(300 * 1024)] }).to_string();
+        let resp = test::call_service(&app, ingest("application/json", large)).await;
+        assert_eq!(resp.status(), StatusCode::OK);
+        let too_large = serde_json::json!({ "events": ["x".repeat(3 * 1024 * 1024)] }).to_string();
+        let resp = test::call_service(&app, ingest("application/json", too_large)).await;
+        assert_eq!(resp.status(), StatusCode::PAYLOAD_TOO_LARGE);
+        let body = r#"{"events":["plain"]}"#.to_string();
+        let resp = test::call_service(&app, ingest("text/plain", body)).await;
+        assert_eq!(resp.status(), StatusCode::UNSUPPORTED_MEDIA_TYPE);
+    }
+
+    #[actix_web::test]
+    #[serial_test::serial]
+    async fn test_ingest_rejects_unknown_envelope_keys() {
+        set_config(test_config());
+        crate::utils::events::flush_events().await;
+        let app = test::init_service(App::new().configure(create_app)).await;
+        let req = test::TestRequest::post()
+            .uri("/api/ingest/event")
+            .insert_header((CONTENT_TYPE, "application/json"))
+            .set_payload(r#"{"events":[{"type":"announcement","audence":{"teams":[3]}}]}"#)
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), StatusCode::BAD_REQUEST);
+        assert!(crate::utils::events::get_events(None).await.is_empty());
+    }
+}
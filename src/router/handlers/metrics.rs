@@ -0,0 +1,110 @@
+use crate::utils::metrics;
+use actix_web::{HttpResponse, Responder};
+
+pub async fn metrics_handler() -> impl Responder {
+    HttpResponse::Ok()
+        .content_type("text/plain; version=0.0.4")
+        .body(metrics::render().await)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::config::app::HttpConfig;
+    use crate::router::{create_app, test_support::*};
+    use crate::values::config::set_config;
+    use actix_web::{App, http::StatusCode, test};
+
+    #[actix_web::test]
+    #[serial_test::serial]
+    async fn test_metrics_endpoint() {
+        use sha2::{Digest, Sha256};
+        let mut cfg = test_config();
+        let http: HttpConfig = toml::from_str(
+            r#"
+            endpoint = "/ingest/event"
+
+            [[keys]]
+            name = "metrics-core"
+            key = "metrics-key-0123456789"
+            topics = ["metrics"]
+
+            [[keys]]
+            name = "metrics-any"
+            key = "metrics-any-0123456789"
+            "#,
+        )
+        .unwrap();
+        cfg.app.events.as_mut().unwrap().http = Some(http);
+        set_config(cfg);
+        let app = test::init_service(App::new().configure(create_app)).await;
+        let req = test::TestRequest::post()
+            .uri("/api/ingest/event")
+            .insert_header((
+                "x-api-key",
+                format!("{:x}", Sha256::digest(b"metrics-key-0123456789")),
+            ))
+            .set_json(serde_json::json!({ "events": [
+                { "type": "message", "body": "a", "topic": "metrics" },
+                { "type": "message", "body": "b", "topic": "metrics" },
+            ]}))
+            .to_request();
+        assert_eq!(test::call_service(&app, req).await.status(), StatusCode::OK);
+        let req = test::TestRequest::post()
+            .uri("/api/ingest/event")
+            .insert_header((
+                "x-api-key",
+                format!("{:x}", Sha256::digest(b"metrics-any-0123456789")),
+            ))
+            .set_json(serde_json::json!({ "events": [
+                { "type": "message", "body": "c", "topic": "run-4711" },
+            ]}))
+            .to_request();
+        assert_eq!(test::call_service(&app, req).await.status(), StatusCode::OK);
+        let req = test::TestRequest::get().uri("/api/notify").to_request();
+        let stream = test::call_service(&app, req).await;
+        let req = test::TestRequest::get().uri("/api/ping").to_request();
+        test::call_service(&app, req).await;
+
+        // With keys configured the endpoint needs one.
+        let req = test::TestRequest::get().uri("/metrics").to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), StatusCode::UNAUTHORIZED);
+        let req = test::TestRequest::get()
+            .uri("/metrics")
+            .insert_header((
+                "x-api-key",
+                format!("{:x}", Sha256::digest(b"metrics-any-0123456789")),
+            ))
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), StatusCode::OK);
+        let body = String::from_utf8(test::read_body(resp).await.to_vec()).unwrap();
+        let value = |name: &str| -> f64 {
+            body.lines()
+                .find_map(|line| line.strip_prefix(name)?.strip_prefix(' ')?.parse().ok())
+                .unwrap_or_else(|| panic!("{} missing from:\n{}", name, body))
+        };
+        assert_eq!(
+            value(r#"rodan_events_ingested_total{key="metrics-core",topic="metrics"}"#),
+            2.0
+        );
+        assert_eq!(
+            value(r#"rodan_events_ingested_total{key="metrics-any",topic="other"}"#),
+            1.0
+        );
+        assert!(value("rodan_sse_connections") >= 1.0);
+        assert_eq!(value("rodan_buffer_max_segments"), 10.0);
+        assert!(value("rodan_buffer_events") >= 2.0);
+        assert!(
+            value(
+                r#"rodan_http_request_duration_seconds_count{method="POST",route="/api/ingest/event"}"#
+            ) >= 1.0
+        );
+        assert!(
+            value(
+                r#"rodan_http_request_duration_seconds_bucket{method="GET",route="/api/ping",le="+Inf"}"#
+            ) >= 1.0
+        );
+        drop(stream);
+    }
+}
@@ -1,7 +1,23 @@
+mod admin;
 mod events;
+mod health;
+mod inbox;
 mod ingester;
+mod metrics;
+mod presence;
+mod receipts;
 mod sse;
 
-pub use events::events_get_handler;
-pub use ingester::events_ingestor;
-pub use sse::sse_handler;
+pub use admin::{
+    admin_events_handler, admin_presence_handler, admin_publish_handler, connections_list_handler,
+    disconnect_handler, disconnect_matching_handler, lift_revocation_handler,
+    revocations_list_handler, revoke_handler,
+};
+pub use events::{events_get_handler, events_history_handler};
+pub use health::{healthz_handler, readyz_handler};
+pub use inbox::{inbox_delete_handler, inbox_handler, inbox_read_all_handler, inbox_read_handler};
+pub use ingester::{MAX_BODY_SIZE, events_ingestor};
+pub use metrics::metrics_handler;
+pub use presence::presence_handler;
+pub use receipts::{ack_handler, unread_handler};
+pub use sse::{sse_handler, ticket_handler};
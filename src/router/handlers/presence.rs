@@ -0,0 +1,96 @@
+use crate::{
+    responses::types::{ErrorResponse, PresenceResponse},
+    utils::{auth::request_claims, presence},
+};
+use actix_web::{HttpRequest, HttpResponse, Responder};
+
+// The current count, so a dashboard has a value before the first change event.
+pub async fn presence_handler(req: HttpRequest) -> impl Responder {
+    match request_claims(&req) {
+        Some(claims) => HttpResponse::Ok().json(PresenceResponse {
+            team_id: claims.team_id,
+            online: presence::online(claims.team_id),
+        }),
+        None => HttpResponse::Unauthorized().json(ErrorResponse {
+            error: "Presence requires a signed-in user".into(),
+        }),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::router::{create_app, test_support::*};
+    use crate::values::config::set_config;
+    use actix_web::{App, http::StatusCode, test};
+
+    #[actix_web::test]
+    #[serial_test::serial]
+    async fn test_team_presence() {
+        use crate::config::app::PresenceConfig;
+        use std::time::Duration;
+        let mut cfg = auth_config();
+        cfg.app.presence = Some(toml::from_str::<PresenceConfig>(r#"debounce = "200ms""#).unwrap());
+        set_config(cfg);
+        let app = test::init_service(App::new().configure(create_app)).await;
+        let open = |user_id: u64| {
+            test::TestRequest::get()
+                .uri("/api/notify?topics=presence")
+                .insert_header((
+                    "Authorization",
+                    format!("Bearer {}", token_for(user_id, 71)),
+                ))
+                .to_request()
+        };
+        let first = test::call_service(&app, open(701)).await;
+        let mut first = Box::pin(first.into_body());
+        next_chunk(&mut first.as_mut()).await;
+        let chunk = next_chunk(&mut first.as_mut()).await;
+        let text = String::from_utf8(chunk.to_vec()).unwrap();
+        assert!(text.starts_with("event: presence\n"), "{}", text);
+        assert!(text.contains(r#""online":1"#), "{}", text);
+        assert!(text.contains(r#""team_id":71"#), "{}", text);
+
+        // A teammate reconnecting within the debounce window causes no event.
+        let second = test::call_service(&app, open(702)).await;
+        drop(second);
+        let second = test::call_service(&app, open(702)).await;
+        let chunk = next_chunk(&mut first.as_mut()).await;
+        assert!(String::from_utf8_lossy(&chunk).contains(r#""online":2"#));
+        let req = test::TestRequest::get()
+            .uri("/api/presence")
+            .insert_header(("Authorization", format!("Bearer {}", token_for(703, 71))))
+            .to_request();
+        let body: serde_json::Value = test::call_and_read_body_json(&app, req).await;
+        assert_eq!(body["online"], 2);
+        let req = test::TestRequest::get()
+            .uri("/api/admin/presence")
+            .insert_header(("Authorization", format!("Bearer {}", admin_token())))
+            .to_request();
+        let body: serde_json::Value = test::call_and_read_body_json(&app, req).await;
+        assert!(
+            body.as_array()
+                .unwrap()
+                .contains(&serde_json::json!({ "team_id": 71, "online": 2 }))
+        );
+
+        drop(second);
+        let chunk = next_chunk(&mut first.as_mut()).await;
+        assert!(String::from_utf8_lossy(&chunk).contains(r#""online":1"#));
+        drop(first);
+        tokio::time::sleep(Duration::from_millis(400)).await;
+        assert_eq!(crate::utils::presence::online(71), 0);
+        // Nothing was stored, and producers cannot publish to the topic.
+        let events = crate::utils::events::get_events(None).await;
+        assert!(events.iter().all(|e| e.topic != "presence"));
+        let req = test::TestRequest::post()
+            .uri("/api/ingest/event")
+            .set_json(serde_json::json!({
+                "events": [{ "type": "presence", "body": "9 online", "topic": "presence" }]
+            }))
+            .to_request();
+        assert_eq!(
+            test::call_service(&app, req).await.status(),
+            StatusCode::BAD_REQUEST
+        );
+    }
+}
@@ -0,0 +1,118 @@
+use crate::{
+    responses::types::{ErrorResponse, UnreadResponse},
+    utils::{auth::request_claims, events::last_event_id, inbox, receipts},
+};
+use actix_web::{HttpRequest, HttpResponse, Responder, web};
+
+#[derive(serde::Deserialize)]
+pub struct AckRequest {
+    pub id: u64,
+}
+
+fn signed_in_required() -> HttpResponse {
+    HttpResponse::Unauthorized().json(ErrorResponse {
+        error: "Read receipts require a signed-in user".into(),
+    })
+}
+
+// Marks everything up to `id` as read and returns the counts that remain.
+pub async fn ack_handler(req: HttpRequest, body: web::Json<AckRequest>) -> impl Responder {
+    let Some(claims) = request_claims(&req) else {
+        return signed_in_required();
+    };
+    if body.id > last_event_id() {
+        return HttpResponse::BadRequest().json(ErrorResponse {
+            error: format!("Unknown event id {}", body.id),
+        });
+    }
+    let cursor = receipts::acknowledge(claims.user_id, body.id);
+    if let Some(store) = inbox::store()
+        && let Err(e) = store.acknowledge(claims.user_id, cursor).await
+    {
+        return HttpResponse::InternalServerError().json(ErrorResponse {
+            error: format!("Failed to save the read cursor: {}", e),
+        });
+    }
+    HttpResponse::Ok().json(UnreadResponse::from(receipts::unread(&claims).await))
+}
+
+pub async fn unread_handler(req: HttpRequest) -> impl Responder {
+    match request_claims(&req) {
+        Some(claims) => {
+            HttpResponse::Ok().json(UnreadResponse::from(receipts::unread(&claims).await))
+        }
+        None => signed_in_required(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::router::{create_app, test_support::*};
+    use crate::values::config::set_config;
+    use actix_web::{App, http::StatusCode, test};
+
+    #[actix_web::test]
+    #[serial_test::serial]
+    async fn test_read_receipts() {
+        use crate::utils::events::{EventDraft, flush_events, last_event_id, push_event};
+        set_config(auth_config());
+        flush_events().await;
+        let app = test::init_service(App::new().configure(create_app)).await;
+        let bearer = || ("Authorization", format!("Bearer {}", token_for(801, 81)));
+        let unread = || {
+            test::TestRequest::get()
+                .uri("/api/events/unread")
+                .insert_header(bearer())
+                .to_request()
+        };
+        let ack = |id: u64| {
+            test::TestRequest::post()
+                .uri("/api/events/ack")
+                .insert_header(bearer())
+                .set_json(serde_json::json!({ "id": id }))
+                .to_request()
+        };
+
+        push_event("for everyone".into()).await.unwrap();
+        let first = push_event(targeted("write-up reviewed", vec![801], vec![]))
+            .await
+            .unwrap();
+        push_event(EventDraft {
+            topic: "hints".into(),
+            ..targeted("hint unlocked", vec![], vec![81])
+        })
+        .await
+        .unwrap();
+        push_event(targeted("someone else", vec![802], vec![82]))
+            .await
+            .unwrap();
+        let body: serde_json::Value = test::call_and_read_body_json(&app, unread()).await;
+        assert_eq!(body["cursor"], 0);
+        assert_eq!(body["unread"], 2);
+        assert_eq!(
+            body["topics"],
+            serde_json::json!({ "default": 1, "hints": 1 })
+        );
+
+        let body: serde_json::Value = test::call_and_read_body_json(&app, ack(first.id)).await;
+        assert_eq!(body["cursor"], first.id);
+        assert_eq!(body["unread"], 1);
+        assert_eq!(body["topics"], serde_json::json!({ "hints": 1 }));
+        // An older acknowledgement does not move the cursor back.
+        let body: serde_json::Value = test::call_and_read_body_json(&app, ack(1)).await;
+        assert_eq!(body["cursor"], first.id);
+
+        let resp = test::call_service(&app, ack(last_event_id() + 1)).await;
+        assert_eq!(resp.status(), StatusCode::BAD_REQUEST);
+        let req = test::TestRequest::get()
+            .uri("/api/events/unread")
+            .to_request();
+        assert_eq!(
+            test::call_service(&app, req).await.status(),
+            StatusCode::UNAUTHORIZED
+        );
+        let body: serde_json::Value =
+            test::call_and_read_body_json(&app, ack(last_event_id())).await;
+        assert_eq!(body["unread"], 0);
+    }
+}
@@ -1,35 +1,794 @@
-use crate::values::events::EVENT_CHANNEL;
-use actix_web::{HttpRequest, HttpResponse, Responder, web::Bytes};
-use futures_util::stream;
-use std::time::Duration;
-use tokio::time::sleep;
+use crate::{
+    responses::types::{ErrorResponse, TicketResponse},
+    utils::{
+        auth::{Claims, bearer_token, decode_jwt, request_claims},
+        connections::{self, ConnectionHandle},
+        events::{Event, first_event_id, get_events_after, in_topics, last_event_id, parse_topics},
+        metrics,
+        presence::{self, PRESENCE_TOPIC, PresenceUpdate},
+        revocation::{self, Subject},
+        sse::{SseFormat, SseFrame},
+        tickets,
+    },
+    values::{config::get_config, events},
+};
+use actix_web::{HttpRequest, HttpResponse, Responder, web, web::Bytes};
+use chrono::Utc;
+use futures_util::{StreamExt, stream};
+use serde_json::json;
+use std::{collections::VecDeque, pin::Pin, time::Duration};
+use tokio::{
+    sync::broadcast::{self, error::RecvError},
+    time::{Instant, Sleep, sleep, sleep_until},
+};
 
-#[derive(serde::Serialize)]
-struct SseMessage<T> {
-    #[serde(rename = "type")]
-    event_type: &'static str,
-    data: T,
+const HEARTBEAT_INTERVAL: Duration = Duration::from_secs(30);
+
+#[derive(serde::Deserialize)]
+pub struct NotifyQuery {
+    #[serde(default)]
+    pub format: SseFormat,
+    pub topics: Option<String>,
+    pub ticket: Option<String>,
+    // For clients that cannot set `Last-Event-ID`, e.g. a new `EventSource`
+    // opened after an `auth-expired` event.
+    pub last_event_id: Option<u64>,
+}
+
+struct Subscription {
+    // A single receiver, so frames go out in id order and `seen` is always a
+    // safe point to resume from.
+    rx: broadcast::Receiver<Event>,
+    seen: u64,
+    pending: VecDeque<SseFrame>,
+    format: SseFormat,
+    claims: Option<Claims>,
+    topics: Vec<String>,
+    expires: Option<Instant>,
+    revocations: Option<broadcast::Receiver<Subject>>,
+    presence: Option<broadcast::Receiver<PresenceUpdate>>,
+    closed: bool,
+    connection: ConnectionHandle,
+    heartbeat: Pin<Box<Sleep>>,
 }
 
-pub async fn sse_handler(_req: HttpRequest) -> impl Responder {
-    let rx = EVENT_CHANNEL.subscribe();
-    let server_events = stream::unfold(rx, |mut rx| async move {
-        let msg = tokio::select! {
-            Ok(event) = rx.recv() => {
-                SseMessage { event_type: "event", data: event }
+impl Subscription {
+    // Any frame counts as a sign of life, so the heartbeat is only due after
+    // a quiet `HEARTBEAT_INTERVAL`.
+    fn emit(&mut self, frame: &SseFrame) -> Bytes {
+        self.heartbeat
+            .as_mut()
+            .reset(Instant::now() + HEARTBEAT_INTERVAL);
+        if frame.id.is_some() {
+            self.connection.record_delivery();
+        } else if frame.event == "heartbeat" {
+            self.connection.record_heartbeat();
+        }
+        frame.encode(self.format)
+    }
+
+    fn close(&mut self, event: &str) {
+        self.closed = true;
+        self.pending
+            .push_back(SseFrame::new(event, json!({ "last_event_id": self.seen })));
+    }
+
+    // A lagged receiver may have skipped the matching revocation, so the
+    // revocation list is checked instead.
+    fn is_revoked(&mut self, result: Result<Subject, RecvError>) -> bool {
+        let Some(claims) = &self.claims else {
+            return false;
+        };
+        match result {
+            Ok(subject) => subject.applies_to(claims),
+            Err(RecvError::Lagged(_)) => revocation::is_revoked(claims),
+            Err(RecvError::Closed) => {
+                self.revocations = None;
+                false
             }
-            _ = sleep(Duration::from_secs(30)) => {
-                SseMessage { event_type: "heartbeat", data: "ping".to_string() }
+        }
+    }
+
+    // Missed presence updates are not made up for; the next one carries the
+    // current count anyway.
+    fn presence_frame(&mut self, result: Result<PresenceUpdate, RecvError>) -> Option<SseFrame> {
+        match result {
+            Ok(update) if update.audience.matches(self.claims.as_ref()) => Some(SseFrame::new(
+                "presence",
+                json!({ "team_id": update.team_id, "online": update.online }),
+            )),
+            Err(RecvError::Closed) => {
+                self.presence = None;
+                None
             }
+            _ => None,
+        }
+    }
+
+    fn wants(&self, event: &Event) -> bool {
+        in_topics(event, &self.topics) && event.audience.matches(self.claims.as_ref())
+    }
+
+    // Refills a lagged receiver from the buffer. When the buffer no longer
+    // holds everything it skipped, the client is told to resync instead.
+    async fn backfill(&mut self, skipped: u64) {
+        let seen = self.seen;
+        let complete = match first_event_id().await {
+            Some(first) => first <= seen + 1,
+            None => last_event_id() <= seen,
         };
+        let events = get_events_after(seen).await;
+        if !complete {
+            self.pending.push_back(SseFrame::new(
+                "resync",
+                json!({
+                    "last_event_id": seen,
+                    "missed": skipped,
+                }),
+            ));
+        }
+        if let Some(last) = events.last() {
+            self.seen = last.id;
+        }
+        let frames: Vec<SseFrame> = events
+            .iter()
+            .filter(|e| self.wants(e))
+            .map(SseFrame::from)
+            .collect();
+        self.pending.extend(frames);
+    }
+}
+
+// The stream ends when the token would no longer pass validation, with the
+// same leeway `decode_jwt` allows.
+fn stream_deadline(claims: Option<&Claims>) -> Option<Instant> {
+    let exp = claims?.exp?;
+    let leeway = get_config()
+        .server
+        .security
+        .jwt_leeway
+        .unwrap_or_else(|| Duration::from_secs(60));
+    let now = Utc::now().timestamp().max(0) as u64;
+    let remaining = exp.saturating_add(leeway.as_secs()).saturating_sub(now);
+    // An expiry too far out to represent is treated as none.
+    Instant::now().checked_add(Duration::from_secs(remaining))
+}
 
-        let payload = serde_json::to_string(&msg).unwrap();
-        let bytes = Bytes::from(format!("{}\n", payload));
-        Some((Ok::<Bytes, actix_web::Error>(bytes), rx))
+async fn until(deadline: Option<Instant>) {
+    match deadline {
+        Some(deadline) => sleep_until(deadline).await,
+        None => std::future::pending().await,
+    }
+}
+
+async fn next_message<T: Clone>(rx: &mut Option<broadcast::Receiver<T>>) -> Result<T, RecvError> {
+    match rx {
+        Some(rx) => rx.recv().await,
+        None => std::future::pending().await,
+    }
+}
+
+fn gap_frame(since: u64, first_available: u64, missed: Option<u64>) -> SseFrame {
+    SseFrame::new(
+        "gap",
+        json!({
+            "last_event_id": since,
+            "first_available_id": first_available,
+            "missed": missed,
+        }),
+    )
+}
+
+async fn replay_frames(since: u64, sub: &mut Subscription) {
+    let latest = last_event_id();
+    // A client ahead of the sequence saw a previous run of the service, so it
+    // is told its position is unknown and gets the whole buffer.
+    let restarted = since > latest;
+    let replay = get_events_after(if restarted { 0 } else { since }).await;
+    let first_available = replay.first().map(|e| e.id).unwrap_or(latest + 1);
+    if restarted {
+        sub.pending
+            .push_back(gap_frame(since, first_available, None));
+    } else if first_available > since + 1 {
+        sub.pending.push_back(gap_frame(
+            since,
+            first_available,
+            Some(first_available - since - 1),
+        ));
+    }
+    sub.seen = replay.last().map(|e| e.id).unwrap_or(latest);
+    let frames: Vec<SseFrame> = replay
+        .iter()
+        .filter(|e| sub.wants(e))
+        .map(SseFrame::from)
+        .collect();
+    sub.pending.extend(frames);
+}
+
+// Exchanges a Bearer JWT for a short-lived, single-use ticket that can be put
+// in the stream URL instead of the token itself.
+pub async fn ticket_handler(req: HttpRequest) -> impl Responder {
+    let claims = match request_claims(&req) {
+        Some(claims) => claims,
+        None => match bearer_token(req.headers()).map(decode_jwt) {
+            Some(Ok(data)) if !revocation::is_revoked(&data.claims) => data.claims,
+            Some(Ok(_)) => {
+                return HttpResponse::Unauthorized().json(ErrorResponse {
+                    error: "Session has been revoked".into(),
+                });
+            }
+            Some(Err(_)) => {
+                return HttpResponse::Unauthorized().json(ErrorResponse {
+                    error: "Invalid token".into(),
+                });
+            }
+            None => {
+                return HttpResponse::Unauthorized().json(ErrorResponse {
+                    error: "Bearer token is required".into(),
+                });
+            }
+        },
+    };
+    let ttl = get_config()
+        .server
+        .security
+        .ticket_ttl
+        .unwrap_or_else(|| Duration::from_secs(10));
+    HttpResponse::Ok().json(TicketResponse {
+        ticket: tickets::issue(claims, ttl),
+        expires_in: ttl.as_secs(),
+    })
+}
+
+pub async fn sse_handler(req: HttpRequest, query: web::Query<NotifyQuery>) -> impl Responder {
+    let since = match req.headers().get("Last-Event-ID") {
+        Some(header_value) => match header_value
+            .to_str()
+            .ok()
+            .and_then(|v| v.trim().parse::<u64>().ok())
+        {
+            Some(id) => Some(id),
+            None => {
+                return HttpResponse::BadRequest().json(ErrorResponse {
+                    error: "Invalid Last-Event-ID header".into(),
+                });
+            }
+        },
+        None => query.last_event_id,
+    };
+    let topics = match parse_topics(query.topics.as_deref()) {
+        Ok(topics) => topics,
+        Err(e) => return HttpResponse::BadRequest().json(ErrorResponse { error: e }),
+    };
+    let format = query.format;
+    // With auth enabled the middleware has already redeemed the ticket.
+    let claims = match (request_claims(&req), &query.ticket) {
+        (Some(claims), _) => Some(claims),
+        (None, Some(ticket)) => match tickets::redeem(ticket) {
+            Some(claims) => Some(claims),
+            None => {
+                return HttpResponse::Unauthorized().json(ErrorResponse {
+                    error: "Invalid or expired ticket".into(),
+                });
+            }
+        },
+        (None, None) => None,
+    };
+    // Listening first means a revocation issued during setup still closes the stream.
+    let revocations = claims.as_ref().map(|_| revocation::subscribe());
+    if claims.as_ref().is_some_and(revocation::is_revoked) {
+        return HttpResponse::Unauthorized().json(ErrorResponse {
+            error: "Session has been revoked".into(),
+        });
+    }
+    // Subscribe before reading the buffer so nothing published in between is
+    // lost; duplicates are dropped by id in the stream.
+    // Several topics share the firehose, filtered by `wants`, since merging
+    // per-topic channels could reorder ids.
+    let seen = last_event_id();
+    let rx = match topics.as_slice() {
+        [topic] => events::subscribe_topic(topic),
+        _ => events::EVENT_CHANNEL.subscribe(),
+    };
+    let presence =
+        (topics.is_empty() || topics.iter().any(|t| t == PRESENCE_TOPIC)).then(presence::subscribe);
+    let expires = stream_deadline(claims.as_ref());
+    let connection = connections::register(
+        claims.clone(),
+        req.connection_info()
+            .realip_remote_addr()
+            .map(str::to_string),
+        req.headers()
+            .get("User-Agent")
+            .and_then(|v| v.to_str().ok())
+            .map(str::to_string),
+    );
+    let mut subscription = Subscription {
+        rx,
+        seen,
+        pending: VecDeque::new(),
+        format,
+        claims,
+        topics,
+        expires,
+        revocations,
+        presence,
+        closed: false,
+        connection,
+        heartbeat: Box::pin(sleep(HEARTBEAT_INTERVAL)),
+    };
+    if let Some(since) = since {
+        replay_frames(since, &mut subscription).await;
+    }
+    let server_events = stream::unfold(subscription, |mut sub| async move {
+        if !sub.closed && sub.expires.is_some_and(|at| at <= Instant::now()) {
+            sub.close("auth-expired");
+        }
+        if let Some(frame) = sub.pending.pop_front() {
+            let bytes = sub.emit(&frame);
+            return Some((Ok::<Bytes, actix_web::Error>(bytes), sub));
+        }
+        if sub.closed {
+            return None;
+        }
+        let frame = loop {
+            tokio::select! {
+                result = sub.rx.recv() => match result {
+                    Ok(event) => {
+                        if event.id <= sub.seen {
+                            continue;
+                        }
+                        sub.seen = event.id;
+                        if sub.wants(&event) {
+                            break SseFrame::from(&event);
+                        }
+                    }
+                    Err(RecvError::Lagged(skipped)) => {
+                        metrics::record_lag(skipped);
+                        sub.backfill(skipped).await;
+                        if let Some(frame) = sub.pending.pop_front() {
+                            break frame;
+                        }
+                    }
+                    Err(RecvError::Closed) => return None,
+                },
+                _ = &mut sub.heartbeat => break SseFrame::new("heartbeat", "ping"),
+                _ = until(sub.expires) => {
+                    sub.close("auth-expired");
+                    if let Some(frame) = sub.pending.pop_front() {
+                        break frame;
+                    }
+                }
+                result = next_message(&mut sub.presence) => {
+                    if let Some(frame) = sub.presence_frame(result) {
+                        break frame;
+                    }
+                }
+                result = next_message(&mut sub.revocations) => {
+                    if sub.is_revoked(result) {
+                        sub.close("revoked");
+                        if let Some(frame) = sub.pending.pop_front() {
+                            break frame;
+                        }
+                    }
+                }
+                _ = sub.connection.disconnected() => {
+                    sub.close("disconnected");
+                    if let Some(frame) = sub.pending.pop_front() {
+                        break frame;
+                    }
+                }
+            }
+        };
+        let bytes = sub.emit(&frame);
+        Some((Ok::<Bytes, actix_web::Error>(bytes), sub))
     });
+    let preamble = match format {
+        SseFormat::Sse => {
+            let retry = get_config()
+                .app
+                .sse_retry
+                .unwrap_or_else(|| Duration::from_secs(3));
+            vec![Ok(SseFrame::retry_only(retry))]
+        }
+        SseFormat::Legacy => Vec::new(),
+    };
     HttpResponse::Ok()
         .append_header(("Content-Type", "text/event-stream"))
         .append_header(("Cache-Control", "no-cache"))
         .append_header(("Connection", "keep-alive"))
-        .streaming(server_events)
+        .streaming(stream::iter(preamble).chain(server_events))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::router::{create_app, test_support::*};
+    use crate::values::config::set_config;
+    use actix_web::{App, body::MessageBody, http::StatusCode, test};
+    use std::pin::pin;
+
+    #[actix_web::test]
+    #[serial_test::serial]
+    async fn test_stream_deadline_saturates() {
+        set_config(test_config());
+        let claims = Claims {
+            exp: Some(u64::MAX),
+            ..Claims::for_test(5, 3)
+        };
+        let deadline = stream_deadline(Some(&claims));
+        assert!(deadline.is_none_or(|at| at > Instant::now() + Duration::from_secs(3600)));
+        let expired = Claims {
+            exp: Some(0),
+            ..Claims::for_test(5, 3)
+        };
+        assert!(stream_deadline(Some(&expired)).is_some_and(|at| at <= Instant::now()));
+        assert!(stream_deadline(None).is_none());
+    }
+
+    #[actix_web::test]
+    #[serial_test::serial]
+    async fn test_notify_emits_sse_frames() {
+        set_config(test_config());
+        let app = test::init_service(App::new().configure(create_app)).await;
+        let req = test::TestRequest::get().uri("/api/notify").to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), StatusCode::OK);
+        let mut body = pin!(resp.into_body());
+        assert_eq!(next_chunk(&mut body).await, Bytes::from("retry: 3000\n\n"));
+        let event = crate::utils::events::push_event("hello".into())
+            .await
+            .unwrap();
+        assert_eq!(next_chunk(&mut body).await, event_frame(&event));
+    }
+
+    #[actix_web::test]
+    #[serial_test::serial]
+    async fn test_notify_legacy_format() {
+        set_config(test_config());
+        let app = test::init_service(App::new().configure(create_app)).await;
+        let req = test::TestRequest::get()
+            .uri("/api/notify?format=legacy")
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), StatusCode::OK);
+        let mut body = pin!(resp.into_body());
+        crate::utils::events::push_event("hello".into())
+            .await
+            .unwrap();
+        let line = next_chunk(&mut body).await;
+        assert!(line.ends_with(b"\n"));
+        let parsed: serde_json::Value = serde_json::from_slice(&line).unwrap();
+        assert_eq!(
+            parsed,
+            serde_json::json!({"type": "event", "data": "hello"})
+        );
+        let req = test::TestRequest::get()
+            .uri("/api/notify?format=xml")
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), StatusCode::BAD_REQUEST);
+    }
+
+    #[actix_web::test]
+    #[serial_test::serial]
+    async fn test_notify_replays_after_last_event_id() {
+        use crate::utils::events::{flush_events, push_event};
+        set_config(test_config());
+        for i in 0..3 {
+            push_event(format!("evicted {}", i).into()).await.unwrap();
+        }
+        flush_events().await;
+        let first = push_event("first".into()).await.unwrap();
+        let second = push_event("second".into()).await.unwrap();
+        let third = push_event("third".into()).await.unwrap();
+        let app = test::init_service(App::new().configure(create_app)).await;
+
+        let req = test::TestRequest::get()
+            .uri("/api/notify")
+            .insert_header(("Last-Event-ID", first.id.to_string()))
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        let mut body = pin!(resp.into_body());
+        next_chunk(&mut body).await;
+        assert_eq!(second.id, first.id + 1);
+        assert_eq!(next_chunk(&mut body).await, event_frame(&second));
+        assert_eq!(next_chunk(&mut body).await, event_frame(&third));
+        let live = push_event("live".into()).await.unwrap();
+        assert_eq!(next_chunk(&mut body).await, event_frame(&live));
+
+        let req = test::TestRequest::get()
+            .uri("/api/notify")
+            .insert_header(("Last-Event-ID", (first.id - 3).to_string()))
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        let mut body = pin!(resp.into_body());
+        next_chunk(&mut body).await;
+        let gap = String::from_utf8(next_chunk(&mut body).await.to_vec()).unwrap();
+        assert!(gap.starts_with("event: gap\n"), "unexpected frame: {}", gap);
+        assert!(gap.contains("\"missed\":2"), "unexpected frame: {}", gap);
+        assert_eq!(next_chunk(&mut body).await, event_frame(&first));
+
+        let req = test::TestRequest::get()
+            .uri("/api/notify")
+            .insert_header(("Last-Event-ID", "not-a-number"))
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), StatusCode::BAD_REQUEST);
+    }
+
+    #[actix_web::test]
+    #[serial_test::serial]
+    async fn test_topic_subscriptions() {
+        use crate::utils::events::{EventDraft, flush_events, push_event};
+        set_config(test_config());
+        flush_events().await;
+        let on_topic = |body: &str, topic: &str| EventDraft {
+            topic: topic.into(),
+            ..EventDraft::from(body)
+        };
+        let app = test::init_service(App::new().configure(create_app)).await;
+        let req = test::TestRequest::get()
+            .uri("/api/notify?topics=announcements,challenges")
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), StatusCode::OK);
+        let mut body = pin!(resp.into_body());
+        next_chunk(&mut body).await;
+        push_event(on_topic("tick", "scoreboard")).await.unwrap();
+        let announcement = push_event(on_topic("welcome", "announcements"))
+            .await
+            .unwrap();
+        assert_eq!(next_chunk(&mut body).await, event_frame(&announcement));
+        let challenge = push_event(on_topic("new challenge", "challenges"))
+            .await
+            .unwrap();
+        assert_eq!(next_chunk(&mut body).await, event_frame(&challenge));
+
+        let req = test::TestRequest::get()
+            .uri("/api/events?topics=scoreboard")
+            .to_request();
+        let resp: Vec<serde_json::Value> = test::call_and_read_body_json(&app, req).await;
+        assert_eq!(resp.len(), 1);
+        assert_eq!(resp[0]["topic"], "scoreboard");
+        assert_eq!(resp[0]["message"]["body"], "tick");
+
+        let req = test::TestRequest::get()
+            .uri("/api/notify?topics=bad%20topic")
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), StatusCode::BAD_REQUEST);
+    }
+
+    #[actix_web::test]
+    #[serial_test::serial]
+    async fn test_multi_topic_stream_keeps_id_order() {
+        use crate::utils::events::{EventDraft, flush_events, push_event};
+        set_config(test_config());
+        flush_events().await;
+        let app = test::init_service(App::new().configure(create_app)).await;
+        let req = test::TestRequest::get()
+            .uri("/api/notify?topics=announcements,challenges")
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        let mut body = pin!(resp.into_body());
+        next_chunk(&mut body).await;
+        let mut pushed = Vec::new();
+        for (i, topic) in ["announcements", "challenges"]
+            .iter()
+            .cycle()
+            .take(6)
+            .enumerate()
+        {
+            let draft = EventDraft {
+                topic: topic.to_string(),
+                ..EventDraft::from(format!("interleaved {}", i))
+            };
+            pushed.push(push_event(draft).await.unwrap());
+        }
+        for event in &pushed[..3] {
+            assert_eq!(next_chunk(&mut body).await, event_frame(event));
+        }
+
+        // Resuming from the last id received misses nothing on either topic.
+        let req = test::TestRequest::get()
+            .uri("/api/notify?topics=announcements,challenges")
+            .insert_header(("Last-Event-ID", pushed[2].id.to_string()))
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        let mut body = pin!(resp.into_body());
+        next_chunk(&mut body).await;
+        for event in &pushed[3..] {
+            assert_eq!(next_chunk(&mut body).await, event_frame(event));
+        }
+    }
+
+    #[actix_web::test]
+    #[serial_test::serial]
+    async fn test_lagging_subscriber_is_backfilled() {
+        use crate::utils::events::{EventDraft, flush_events, push_event};
+        let mut cfg = test_config();
+        cfg.app.event_channel_capacity = Some(2);
+        set_config(cfg);
+        flush_events().await;
+        let on_topic = |body: String| EventDraft {
+            topic: "lag-backfill".into(),
+            ..EventDraft::from(body)
+        };
+        let app = test::init_service(App::new().configure(create_app)).await;
+        let req = test::TestRequest::get()
+            .uri("/api/notify?topics=lag-backfill")
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        let mut body = pin!(resp.into_body());
+        next_chunk(&mut body).await;
+        let mut pushed = Vec::new();
+        for i in 0..5 {
+            pushed.push(push_event(on_topic(format!("burst {}", i))).await.unwrap());
+        }
+        for event in &pushed {
+            assert_eq!(next_chunk(&mut body).await, event_frame(event));
+        }
+        let after = push_event(on_topic("after".into())).await.unwrap();
+        assert_eq!(next_chunk(&mut body).await, event_frame(&after));
+    }
+
+    #[actix_web::test]
+    #[serial_test::serial]
+    async fn test_lagging_subscriber_gets_resync() {
+        use crate::utils::events::{EventDraft, flush_events, push_event};
+        let mut cfg = test_config();
+        cfg.app.event_channel_capacity = Some(2);
+        set_config(cfg);
+        let on_topic = |body: String| EventDraft {
+            topic: "lag-resync".into(),
+            ..EventDraft::from(body)
+        };
+        let app = test::init_service(App::new().configure(create_app)).await;
+        let req = test::TestRequest::get()
+            .uri("/api/notify?topics=lag-resync")
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        let mut body = pin!(resp.into_body());
+        next_chunk(&mut body).await;
+        let mut pushed = Vec::new();
+        for i in 0..5 {
+            pushed.push(push_event(on_topic(format!("lost {}", i))).await.unwrap());
+        }
+        flush_events().await;
+        let resync = String::from_utf8(next_chunk(&mut body).await.to_vec()).unwrap();
+        assert!(
+            resync.starts_with("event: resync\n"),
+            "unexpected frame: {}",
+            resync
+        );
+        assert!(
+            resync.contains("\"missed\":3"),
+            "unexpected frame: {}",
+            resync
+        );
+        assert_eq!(next_chunk(&mut body).await, event_frame(&pushed[3]));
+        assert_eq!(next_chunk(&mut body).await, event_frame(&pushed[4]));
+        let after = push_event(on_topic("after".into())).await.unwrap();
+        assert_eq!(next_chunk(&mut body).await, event_frame(&after));
+    }
+
+    #[actix_web::test]
+    #[serial_test::serial]
+    async fn test_subscription_tickets() {
+        use crate::utils::events::push_event;
+        for auth_required in [true, false] {
+            let mut cfg = auth_config();
+            cfg.app.auth_required = auth_required;
+            set_config(cfg);
+            let app = test::init_service(App::new().configure(create_app)).await;
+            let req = test::TestRequest::post()
+                .uri("/api/notify/ticket")
+                .to_request();
+            let resp = test::call_service(&app, req).await;
+            assert_eq!(resp.status(), StatusCode::UNAUTHORIZED);
+
+            let req = test::TestRequest::post()
+                .uri("/api/notify/ticket")
+                .insert_header(("Authorization", format!("Bearer {}", token_for(5, 3))))
+                .to_request();
+            let body: serde_json::Value = test::call_and_read_body_json(&app, req).await;
+            assert_eq!(body["expires_in"], 10);
+            let ticket = body["ticket"].as_str().unwrap().to_string();
+
+            let req = test::TestRequest::get()
+                .uri(&format!("/api/notify?ticket={}", ticket))
+                .to_request();
+            let resp = test::call_service(&app, req).await;
+            assert_eq!(resp.status(), StatusCode::OK);
+            let mut body = pin!(resp.into_body());
+            next_chunk(&mut body).await;
+            push_event(targeted("team 4 only", vec![], vec![4]))
+                .await
+                .unwrap();
+            let own = push_event(targeted("team 3 only", vec![], vec![3]))
+                .await
+                .unwrap();
+            assert_eq!(next_chunk(&mut body).await, event_frame(&own));
+
+            let req = test::TestRequest::get()
+                .uri(&format!("/api/notify?ticket={}", ticket))
+                .to_request();
+            let resp = test::call_service(&app, req).await;
+            assert_eq!(resp.status(), StatusCode::UNAUTHORIZED);
+        }
+    }
+
+    #[actix_web::test]
+    #[serial_test::serial]
+    async fn test_stream_ends_when_token_expires() {
+        use crate::utils::events::push_event;
+        let mut cfg = auth_config();
+        cfg.server.security.jwt_leeway = Some(std::time::Duration::ZERO);
+        set_config(cfg);
+        // The deadline is a tokio timer, so paused time reaches it at once.
+        tokio::time::pause();
+        let app = test::init_service(App::new().configure(create_app)).await;
+        let now = chrono::Utc::now().timestamp();
+        let short_lived = jsonwebtoken::encode(
+            &jsonwebtoken::Header::default(),
+            &serde_json::json!({
+                "user_id": 5,
+                "username": "user5",
+                "team_id": 3,
+                "iss": "rodan",
+                "iat": now,
+                "exp": now + 1,
+            }),
+            &jsonwebtoken::EncodingKey::from_secret(b"test-secret-123"),
+        )
+        .unwrap();
+        let req = test::TestRequest::get()
+            .uri("/api/notify")
+            .insert_header(("Authorization", format!("Bearer {}", short_lived)))
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), StatusCode::OK);
+        let mut body = pin!(resp.into_body());
+        next_chunk(&mut body).await;
+        let seen = push_event(targeted("before expiry", vec![], vec![3]))
+            .await
+            .unwrap();
+        assert_eq!(next_chunk(&mut body).await, event_frame(&seen));
+
+        let rest = std::future::poll_fn(|cx| body.as_mut().poll_next(cx));
+        let chunk = match tokio::time::timeout(std::time::Duration::from_secs(4), rest).await {
+            Ok(Some(Ok(bytes))) => bytes,
+            _ => panic!("Expected an auth-expired frame"),
+        };
+        let expired = SseFrame::new(
+            "auth-expired",
+            serde_json::json!({ "last_event_id": seen.id }),
+        );
+        assert_eq!(chunk, expired.encode(SseFormat::Sse));
+        let end = std::future::poll_fn(|cx| body.as_mut().poll_next(cx)).await;
+        assert!(end.is_none(), "Expected the stream to close");
+
+        // A refreshed ticket picks up where the expired stream stopped.
+        let missed = push_event(targeted("after expiry", vec![], vec![3]))
+            .await
+            .unwrap();
+        let req = test::TestRequest::post()
+            .uri("/api/notify/ticket")
+            .insert_header(("Authorization", format!("Bearer {}", token_for(5, 3))))
+            .to_request();
+        let ticket: serde_json::Value = test::call_and_read_body_json(&app, req).await;
+        let req = test::TestRequest::get()
+            .uri(&format!(
+                "/api/notify?ticket={}&last_event_id={}",
+                ticket["ticket"].as_str().unwrap(),
+                seen.id
+            ))
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), StatusCode::OK);
+        let mut body = pin!(resp.into_body());
+        next_chunk(&mut body).await;
+        assert_eq!(next_chunk(&mut body).await, event_frame(&missed));
+    }
 }
@@ -1,58 +1,150 @@
 mod handlers;
+#[cfg(test)]
+pub(crate) mod test_support;
 
 use crate::{
+    config::app::RouteGroup,
     responses::{not_found_handler, ping_response},
     utils::middlewares,
     values,
 };
-use actix_web::{
-    middleware::{Logger, from_fn},
-    web,
-};
+use actix_web::{middleware::from_fn, web};
 
 pub fn create_app(cfg: &mut web::ServiceConfig) {
     let config = values::config::get_config();
+    let policy = |group: RouteGroup| {
+        from_fn(move |req, next| middlewares::auth::policy_middleware(group, req, next))
+    };
     let mut api_scope = web::scope("/api")
-        .route("/ping", web::get().to(ping_response))
-        .route("/notify", web::get().to(handlers::sse_handler))
-        .route("/events", web::get().to(handlers::events_get_handler));
+        .service(
+            web::resource("/ping")
+                .wrap(policy(RouteGroup::Ping))
+                .route(web::get().to(ping_response)),
+        )
+        .service(
+            web::resource("/notify")
+                .wrap(policy(RouteGroup::Notify))
+                .route(web::get().to(handlers::sse_handler)),
+        )
+        .service(
+            web::resource("/notify/ticket")
+                .wrap(policy(RouteGroup::Notify))
+                .route(web::post().to(handlers::ticket_handler)),
+        )
+        .service(
+            web::resource("/presence")
+                .wrap(policy(RouteGroup::Notify))
+                .route(web::get().to(handlers::presence_handler)),
+        )
+        .service(
+            web::resource("/events")
+                .wrap(policy(RouteGroup::Events))
+                .route(web::get().to(handlers::events_get_handler)),
+        )
+        .service(
+            web::resource("/events/history")
+                .wrap(policy(RouteGroup::Events))
+                .route(web::get().to(handlers::events_history_handler)),
+        )
+        .service(
+            web::resource("/events/ack")
+                .wrap(policy(RouteGroup::Events))
+                .route(web::post().to(handlers::ack_handler)),
+        )
+        .service(
+            web::resource("/events/unread")
+                .wrap(policy(RouteGroup::Events))
+                .route(web::get().to(handlers::unread_handler)),
+        )
+        .service(
+            web::resource("/admin/events")
+                .app_data(web::PayloadConfig::new(handlers::MAX_BODY_SIZE))
+                .wrap(policy(RouteGroup::Admin))
+                .route(web::get().to(handlers::admin_events_handler))
+                .route(web::post().to(handlers::admin_publish_handler)),
+        )
+        .service(
+            web::resource("/admin/revocations")
+                .wrap(policy(RouteGroup::Admin))
+                .route(web::get().to(handlers::revocations_list_handler))
+                .route(web::post().to(handlers::revoke_handler)),
+        )
+        .service(
+            web::resource("/admin/revocations/{kind}/{id}")
+                .wrap(policy(RouteGroup::Admin))
+                .route(web::delete().to(handlers::lift_revocation_handler)),
+        )
+        .service(
+            web::resource("/admin/connections")
+                .wrap(policy(RouteGroup::Admin))
+                .route(web::get().to(handlers::connections_list_handler))
+                .route(web::delete().to(handlers::disconnect_matching_handler)),
+        )
+        .service(
+            web::resource("/admin/presence")
+                .wrap(policy(RouteGroup::Admin))
+                .route(web::get().to(handlers::admin_presence_handler)),
+        )
+        .service(
+            web::resource("/admin/connections/{id}")
+                .wrap(policy(RouteGroup::Admin))
+                .route(web::delete().to(handlers::disconnect_handler)),
+        );
     if let Some(events) = &config.app.events {
         if let Some(http) = &events.http {
-            api_scope = api_scope.route(&http.endpoint, web::post().to(handlers::events_ingestor))
+            api_scope = api_scope.service(
+                web::resource(&http.endpoint)
+                    .app_data(web::PayloadConfig::new(handlers::MAX_BODY_SIZE))
+                    .wrap(policy(RouteGroup::Ingest))
+                    .route(web::post().to(handlers::events_ingestor)),
+            )
         }
     } else {
         panic!("No events ingestion path is specified")
     }
-    if config.app.auth_required {
-        if config.server.production {
-            cfg.service(
-                api_scope
-                    .wrap(from_fn(middlewares::auth::auth_middleware))
-                    .wrap(from_fn(middlewares::log::log_middleware))
-                    .wrap(middlewares::cors::cors_middleware()),
-            );
-        } else {
-            cfg.service(
-                api_scope
-                    .wrap(from_fn(middlewares::auth::auth_middleware))
-                    .wrap(Logger::default())
-                    .wrap(middlewares::cors::cors_middleware()),
+    if config.app.inbox.is_some() {
+        api_scope = api_scope
+            .service(
+                web::resource("/inbox")
+                    .wrap(policy(RouteGroup::Events))
+                    .route(web::get().to(handlers::inbox_handler)),
+            )
+            .service(
+                web::resource("/inbox/read")
+                    .wrap(policy(RouteGroup::Events))
+                    .route(web::post().to(handlers::inbox_read_handler)),
+            )
+            .service(
+                web::resource("/inbox/read-all")
+                    .wrap(policy(RouteGroup::Events))
+                    .route(web::post().to(handlers::inbox_read_all_handler)),
+            )
+            .service(
+                web::resource("/inbox/{id}")
+                    .wrap(policy(RouteGroup::Events))
+                    .route(web::delete().to(handlers::inbox_delete_handler)),
             );
-        }
+    }
+    let api_scope = api_scope.wrap(from_fn(middlewares::metrics::metrics_middleware));
+    cfg.route("/healthz", web::get().to(handlers::healthz_handler))
+        .route("/readyz", web::get().to(handlers::readyz_handler));
+    cfg.service(
+        web::resource("/metrics")
+            .wrap(policy(RouteGroup::Metrics))
+            .route(web::get().to(handlers::metrics_handler)),
+    );
+    if config.server.production {
+        cfg.service(
+            api_scope
+                .wrap(from_fn(middlewares::log::log_middleware))
+                .wrap(middlewares::cors::cors_middleware()),
+        );
     } else {
-        if config.server.production {
-            cfg.service(
-                api_scope
-                    .wrap(from_fn(middlewares::log::log_middleware))
-                    .wrap(middlewares::cors::cors_middleware()),
-            );
-        } else {
-            cfg.service(
-                api_scope
-                    .wrap(Logger::default())
-                    .wrap(middlewares::cors::cors_middleware()),
-            );
-        }
+        cfg.service(
+            api_scope
+                .wrap(middlewares::log::access_logger())
+                .wrap(middlewares::cors::cors_middleware()),
+        );
     }
     cfg.default_service(web::route().to(not_found_handler));
 }
@@ -83,6 +175,7 @@ mod tests {
                 production: false,
                 cors_url: vec!["http://localhost:3000".into()],
                 security: Default::default(),
+                ..Default::default()
             },
             app: AppConfig {
                 auth_required: false,
@@ -91,6 +184,7 @@ mod tests {
                         endpoint: "/ingest/event".into(),
                         api_key: Some("1234567890123456".into()),
                         hashed_api_key: Some("1234567890123456".into()),
+                        ..Default::default()
                     }),
                 }),
                 event_logging: false,
@@ -98,6 +192,7 @@ mod tests {
                 events_logfile: Some("events.log".into()),
                 event_max_segments: Some(10),
                 event_segment_size: Some(100),
+                ..Default::default()
             },
         };
         set_config(cfg);
@@ -136,7 +231,8 @@ mod tests {
         );
         let events = crate::utils::events::get_events(None).await;
         assert_eq!(events.len(), 1);
-        assert_eq!(events[0].payload, "test");
+        assert_eq!(events[0].payload.kind, "message");
+        assert_eq!(events[0].payload.body.as_deref(), Some("test"));
     }
 
     #[actix_web::test]
@@ -153,11 +249,14 @@ mod tests {
                 events_logfile: None,
                 event_max_segments: Some(10),
                 event_segment_size: Some(100),
+                ..Default::default()
             },
         };
         set_config(cfg);
         let result = catch_unwind(AssertUnwindSafe(|| {
-            let _ = actix_web::test::init_service(actix_web::App::new().configure(create_app));
+            drop(actix_web::test::init_service(
+                actix_web::App::new().configure(create_app),
+            ));
         }));
         assert!(result.is_err(), "Expected panic when events config is None");
     }
@@ -0,0 +1,100 @@
+use crate::config::Config;
+use crate::config::app::{AppConfig, EventsConfig, HttpConfig};
+use crate::config::server::ServerConfig;
+use crate::utils::events::{Audience, Event, EventDraft};
+use crate::utils::sse::{SseFormat, SseFrame};
+use actix_web::{body::MessageBody, web::Bytes};
+use std::pin::Pin;
+
+pub(crate) fn test_config() -> Config {
+    Config {
+        server: ServerConfig {
+            host: "127.0.0.1".into(),
+            port: 8080,
+            production: false,
+            cors_url: vec!["http://localhost:3000".into()],
+            security: Default::default(),
+            ..Default::default()
+        },
+        app: AppConfig {
+            events: Some(EventsConfig {
+                http: Some(HttpConfig {
+                    endpoint: "/ingest/event".into(),
+                    api_key: None,
+                    hashed_api_key: None,
+                    ..Default::default()
+                }),
+            }),
+            event_max_segments: Some(10),
+            event_segment_size: Some(100),
+            ..Default::default()
+        },
+    }
+}
+
+pub(crate) fn token_for(user_id: u64, team_id: u64) -> String {
+    let now = chrono::Utc::now().timestamp();
+    let claims = serde_json::json!({
+        "user_id": user_id,
+        "username": format!("user{}", user_id),
+        "team_id": team_id,
+        "iss": "rodan",
+        "iat": now,
+        "exp": now + 600,
+    });
+    jsonwebtoken::encode(
+        &jsonwebtoken::Header::default(),
+        &claims,
+        &jsonwebtoken::EncodingKey::from_secret(b"test-secret-123"),
+    )
+    .unwrap()
+}
+
+pub(crate) fn admin_token() -> String {
+    let now = chrono::Utc::now().timestamp();
+    let claims = serde_json::json!({
+        "user_id": 1,
+        "username": "organizer",
+        "team_id": 0,
+        "role": "admin",
+        "iss": "rodan",
+        "iat": now,
+        "exp": now + 600,
+    });
+    jsonwebtoken::encode(
+        &jsonwebtoken::Header::default(),
+        &claims,
+        &jsonwebtoken::EncodingKey::from_secret(b"test-secret-123"),
+    )
+    .unwrap()
+}
+
+pub(crate) fn auth_config() -> Config {
+    let mut cfg = test_config();
+    cfg.server.security.jwt_secret = "test-secret-123".into();
+    cfg.app.auth_required = true;
+    cfg
+}
+
+pub(crate) fn targeted(body: &str, users: Vec<u64>, teams: Vec<u64>) -> EventDraft {
+    EventDraft {
+        audience: Audience {
+            users,
+            teams,
+            ..Default::default()
+        },
+        ..EventDraft::from(body)
+    }
+}
+
+pub(crate) fn event_frame(event: &Event) -> Bytes {
+    SseFrame::from(event).encode(SseFormat::Sse)
+}
+
+pub(crate) async fn next_chunk<B: MessageBody>(body: &mut Pin<&mut B>) -> Bytes {
+    let chunk = std::future::poll_fn(|cx| body.as_mut().poll_next(cx));
+    match tokio::time::timeout(std::time::Duration::from_secs(2), chunk).await {
+        Ok(Some(Ok(bytes))) => bytes,
+        _ => panic!("Expected a chunk from the event stream"),
+    }
+}
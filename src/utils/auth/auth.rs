@@ -1,23 +1,269 @@
-use crate::values;
-use jsonwebtoken::{Algorithm, DecodingKey, TokenData, Validation, decode, errors::Error};
+use crate::{
+    config::{app::ApiKey, app::HttpConfig, jwt::is_hmac},
+    values,
+};
+use actix_web::{HttpMessage, HttpRequest, http::header::HeaderMap};
+use jsonwebtoken::{
+    DecodingKey, TokenData, Validation, decode, decode_header,
+    errors::{Error, ErrorKind},
+};
 use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct Claims {
     pub user_id: u64,
     pub username: String,
     pub team_id: u64,
+    #[serde(default)]
+    pub exp: Option<u64>,
+    #[serde(default)]
+    pub jti: Option<String>,
+    #[serde(skip)]
+    pub roles: Vec<String>,
+}
+
+impl Claims {
+    pub fn has_role(&self, role: &str) -> bool {
+        self.roles.iter().any(|r| r == role)
+    }
+
+    #[cfg(test)]
+    pub fn for_test(user_id: u64, team_id: u64) -> Claims {
+        Claims {
+            user_id,
+            username: format!("user{}", user_id),
+            team_id,
+            exp: None,
+            jti: None,
+            roles: Vec::new(),
+        }
+    }
+}
+
+#[derive(Deserialize, Clone)]
+struct RawClaims {
+    #[serde(flatten)]
+    claims: Claims,
+    #[serde(flatten)]
+    rest: Map<String, Value>,
+}
+
+// The claim may be a single role or a list of roles or permissions, and a
+// dotted name reaches into nested objects, e.g. `realm_access.roles`.
+fn read_roles(rest: &Map<String, Value>, name: &str) -> Vec<String> {
+    let mut parts = name.split('.');
+    let mut value = parts.next().and_then(|first| rest.get(first));
+    for part in parts {
+        value = value.and_then(|v| v.get(part));
+    }
+    match value {
+        Some(Value::String(role)) => vec![role.clone()],
+        Some(Value::Array(roles)) => roles
+            .iter()
+            .filter_map(|r| r.as_str().map(str::to_string))
+            .collect(),
+        _ => Vec::new(),
+    }
+}
+
+fn with_roles(data: TokenData<RawClaims>, claim: &str) -> TokenData<Claims> {
+    let mut claims = data.claims.claims;
+    claims.roles = read_roles(&data.claims.rest, claim);
+    TokenData {
+        header: data.header,
+        claims,
+    }
 }
 
 pub fn decode_jwt(token: &str) -> Result<TokenData<Claims>, Error> {
-    let mut valid = Validation::new(Algorithm::HS256);
-    let key = values::config::get_config()
-        .server
-        .security
-        .jwt_secret
-        .clone();
-    valid.set_required_spec_claims(&["exp", "iss", "iat"]);
+    let cfg = values::config::get_config();
+    let security = &cfg.server.security;
+    let header = decode_header(token)?;
+    if !security.algorithms().contains(&header.alg) {
+        return Err(ErrorKind::InvalidAlgorithm.into());
+    }
+    let mut valid = Validation::new(header.alg);
+    let mut required = vec!["exp", "iss", "iat"];
+    if !security.jwt_audience.is_empty() {
+        valid.set_audience(&security.jwt_audience);
+        required.push("aud");
+    }
+    valid.set_required_spec_claims(&required);
     valid.set_issuer(&["rodan"]);
     valid.validate_exp = true;
-    decode::<Claims>(token, &DecodingKey::from_secret(key.as_bytes()), &valid)
+    if let Some(leeway) = security.jwt_leeway {
+        valid.leeway = leeway.as_secs();
+    }
+    let claim = security.role_claim();
+    if is_hmac(&header.alg) {
+        let key = DecodingKey::from_secret(security.jwt_secret.as_bytes());
+        return decode::<RawClaims>(token, &key, &valid).map(|data| with_roles(data, claim));
+    }
+    let mut result = Err(ErrorKind::InvalidSignature.into());
+    for key in security.public_keys_for(header.kid.as_deref(), header.alg) {
+        result = decode::<RawClaims>(token, key, &valid);
+        if result.is_ok() {
+            break;
+        }
+    }
+    result.map(|data| with_roles(data, claim))
+}
+
+pub fn bearer_token(headers: &HeaderMap) -> Option<&str> {
+    headers
+        .get("Authorization")?
+        .to_str()
+        .ok()?
+        .strip_prefix("Bearer ")
+}
+
+pub fn request_claims(req: &HttpRequest) -> Option<Claims> {
+    req.extensions().get::<Claims>().cloned()
+}
+
+pub fn match_api_key(http_cfg: &HttpConfig, headers: &HeaderMap) -> Result<ApiKey, String> {
+    let presented = headers
+        .get("x-api-key")
+        .and_then(|v| v.to_str().ok())
+        .unwrap_or_default();
+    // Every key is compared so the time taken does not depend on which one matched.
+    http_cfg
+        .all_keys()
+        .into_iter()
+        .fold(None, |found, key| {
+            if constant_time_eq(key.hashed_key.as_bytes(), presented.as_bytes()) {
+                Some(key)
+            } else {
+                found
+            }
+        })
+        .ok_or_else(|| "Invalid API key".into())
+}
+
+pub fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    if a.len() != b.len() {
+        return false;
+    }
+    a.iter().zip(b).fold(0u8, |acc, (x, y)| acc | (x ^ y)) == 0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::{Config, jwt::JwtKeyFile};
+    use jsonwebtoken::{Algorithm, EncodingKey, Header, encode};
+    use serial_test::serial;
+
+    fn fixture(name: &str) -> String {
+        format!("{}/tests/fixtures/jwt/{}", env!("CARGO_MANIFEST_DIR"), name)
+    }
+
+    async fn set_security(algorithms: Vec<Algorithm>) {
+        let mut cfg = Config::default();
+        let security = &mut cfg.server.security;
+        security.jwt_secret = "test-secret-123".into();
+        security.jwt_algorithms = algorithms;
+        security.jwt_keys = vec![
+            JwtKeyFile {
+                kid: None,
+                algorithm: Algorithm::ES256,
+                path: fixture("ec-public.pem"),
+            },
+            JwtKeyFile {
+                kid: Some("ed".into()),
+                algorithm: Algorithm::EdDSA,
+                path: fixture("ed25519-public.pem"),
+            },
+        ];
+        security.jwks_file = Some(fixture("jwks.json"));
+        security.jwt_audience = vec!["rodan-notifications".into()];
+        security.validate().unwrap();
+        security.load_keys().await.unwrap();
+        values::config::set_config(cfg);
+    }
+
+    fn token(alg: Algorithm, kid: Option<&str>, key: &EncodingKey, aud: &str) -> String {
+        let now = chrono::Utc::now().timestamp();
+        let claims = serde_json::json!({
+            "user_id": 7,
+            "username": "player",
+            "team_id": 3,
+            "iss": "rodan",
+            "aud": aud,
+            "iat": now,
+            "exp": now + 600,
+        });
+        let mut header = Header::new(alg);
+        header.kid = kid.map(str::to_string);
+        encode(&header, &claims, key).unwrap()
+    }
+
+    fn private_key(alg: Algorithm) -> EncodingKey {
+        match alg {
+            Algorithm::RS256 => {
+                EncodingKey::from_rsa_pem(&std::fs::read(fixture("rsa-private.pem")).unwrap())
+            }
+            Algorithm::ES256 => {
+                EncodingKey::from_ec_pem(&std::fs::read(fixture("ec-private.pem")).unwrap())
+            }
+            Algorithm::EdDSA => {
+                EncodingKey::from_ed_pem(&std::fs::read(fixture("ed25519-private.pem")).unwrap())
+            }
+            _ => Ok(EncodingKey::from_secret(b"test-secret-123")),
+        }
+        .unwrap()
+    }
+
+    #[tokio::test]
+    #[serial]
+    async fn test_asymmetric_tokens() {
+        set_security(vec![
+            Algorithm::RS256,
+            Algorithm::ES256,
+            Algorithm::EdDSA,
+            Algorithm::HS256,
+        ])
+        .await;
+        for (alg, kid) in [
+            (Algorithm::RS256, Some("core-rsa")),
+            (Algorithm::ES256, None),
+            (Algorithm::EdDSA, Some("ed")),
+            (Algorithm::HS256, None),
+        ] {
+            let jwt = token(alg, kid, &private_key(alg), "rodan-notifications");
+            let data = decode_jwt(&jwt).unwrap_or_else(|e| panic!("{:?}: {}", alg, e));
+            assert_eq!(data.claims.team_id, 3);
+        }
+        let jwt = token(
+            Algorithm::RS256,
+            Some("unknown"),
+            &private_key(Algorithm::RS256),
+            "rodan-notifications",
+        );
+        assert!(decode_jwt(&jwt).is_err());
+        let jwt = token(
+            Algorithm::ES256,
+            None,
+            &private_key(Algorithm::ES256),
+            "someone-else",
+        );
+        assert!(decode_jwt(&jwt).is_err());
+    }
+
+    #[tokio::test]
+    #[serial]
+    async fn test_disallowed_algorithm_rejected() {
+        set_security(vec![Algorithm::RS256, Algorithm::ES256, Algorithm::EdDSA]).await;
+        let jwt = token(
+            Algorithm::HS256,
+            None,
+            &private_key(Algorithm::HS256),
+            "rodan-notifications",
+        );
+        assert_eq!(
+            decode_jwt(&jwt).unwrap_err().kind(),
+            &ErrorKind::InvalidAlgorithm
+        );
+    }
 }
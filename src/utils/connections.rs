@@ -0,0 +1,183 @@
+use crate::utils::{auth::Claims, presence};
+use chrono::{DateTime, Utc};
+use once_cell::sync::Lazy;
+use std::{
+    collections::HashMap,
+    ops::Deref,
+    sync::{
+        Arc, Mutex,
+        atomic::{AtomicU64, Ordering},
+    },
+};
+use tokio::sync::Notify;
+
+pub struct Connection {
+    pub id: u64,
+    pub claims: Option<Claims>,
+    pub ip: Option<String>,
+    pub user_agent: Option<String>,
+    pub connected: DateTime<Utc>,
+    delivered: AtomicU64,
+    last_heartbeat: Mutex<Option<DateTime<Utc>>>,
+    disconnect: Notify,
+}
+
+impl Connection {
+    pub fn user_id(&self) -> Option<u64> {
+        self.claims.as_ref().map(|c| c.user_id)
+    }
+
+    pub fn team_id(&self) -> Option<u64> {
+        self.claims.as_ref().map(|c| c.team_id)
+    }
+
+    pub fn delivered(&self) -> u64 {
+        self.delivered.load(Ordering::Relaxed)
+    }
+
+    pub fn last_heartbeat(&self) -> Option<DateTime<Utc>> {
+        *self
+            .last_heartbeat
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+    }
+
+    pub fn record_delivery(&self) {
+        self.delivered.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn record_heartbeat(&self) {
+        *self
+            .last_heartbeat
+            .lock()
+            .unwrap_or_else(|e| e.into_inner()) = Some(Utc::now());
+    }
+
+    // Resolves once an admin disconnects this stream; a request made before
+    // anyone waits is kept until the stream next polls.
+    pub async fn disconnected(&self) {
+        self.disconnect.notified().await
+    }
+}
+
+static NEXT_ID: AtomicU64 = AtomicU64::new(1);
+static CONNECTIONS: Lazy<Mutex<HashMap<u64, Arc<Connection>>>> =
+    Lazy::new(|| Mutex::new(HashMap::new()));
+
+fn connections() -> std::sync::MutexGuard<'static, HashMap<u64, Arc<Connection>>> {
+    CONNECTIONS.lock().unwrap_or_else(|e| e.into_inner())
+}
+
+// Owned by the stream; the connection is unregistered when it is dropped.
+pub struct ConnectionHandle(Arc<Connection>);
+
+impl Deref for ConnectionHandle {
+    type Target = Connection;
+
+    fn deref(&self) -> &Connection {
+        &self.0
+    }
+}
+
+impl Drop for ConnectionHandle {
+    fn drop(&mut self) {
+        connections().remove(&self.0.id);
+        if let Some(claims) = &self.0.claims {
+            presence::left(claims);
+        }
+    }
+}
+
+pub fn register(
+    claims: Option<Claims>,
+    ip: Option<String>,
+    user_agent: Option<String>,
+) -> ConnectionHandle {
+    let connection = Arc::new(Connection {
+        id: NEXT_ID.fetch_add(1, Ordering::Relaxed),
+        claims,
+        ip,
+        user_agent,
+        connected: Utc::now(),
+        delivered: AtomicU64::new(0),
+        last_heartbeat: Mutex::new(None),
+        disconnect: Notify::new(),
+    });
+    connections().insert(connection.id, connection.clone());
+    if let Some(claims) = &connection.claims {
+        presence::joined(claims);
+    }
+    ConnectionHandle(connection)
+}
+
+pub fn count() -> usize {
+    connections().len()
+}
+
+// Oldest first; a filter on user or team skips anonymous streams.
+pub fn list(user_id: Option<u64>, team_id: Option<u64>) -> Vec<Arc<Connection>> {
+    let mut matching: Vec<_> = connections()
+        .values()
+        .filter(|c| user_id.is_none() || c.user_id() == user_id)
+        .filter(|c| team_id.is_none() || c.team_id() == team_id)
+        .cloned()
+        .collect();
+    matching.sort_by_key(|c| c.id);
+    matching
+}
+
+pub fn disconnect(id: u64) -> bool {
+    match connections().get(&id) {
+        Some(connection) => {
+            connection.disconnect.notify_one();
+            true
+        }
+        None => false,
+    }
+}
+
+pub fn disconnect_matching(user_id: Option<u64>, team_id: Option<u64>) -> usize {
+    let matching = list(user_id, team_id);
+    for connection in &matching {
+        connection.disconnect.notify_one();
+    }
+    matching.len()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serial_test::serial;
+
+    #[tokio::test]
+    #[serial]
+    async fn test_registry_tracks_streams() {
+        let first = register(Some(Claims::for_test(501, 50)), None, None);
+        let second = register(
+            Some(Claims::for_test(502, 50)),
+            Some("10.0.0.1".into()),
+            None,
+        );
+        let anonymous = register(None, None, None);
+        assert_eq!(list(None, Some(50)).len(), 2);
+        assert_eq!(list(Some(502), None)[0].id, second.id);
+        assert!(list(Some(503), None).is_empty());
+
+        first.record_delivery();
+        first.record_heartbeat();
+        assert_eq!(first.delivered(), 1);
+        assert!(first.last_heartbeat().is_some());
+
+        assert!(disconnect(first.id));
+        tokio::time::timeout(std::time::Duration::from_secs(1), first.disconnected())
+            .await
+            .unwrap();
+        assert_eq!(disconnect_matching(Some(502), None), 1);
+        let id = anonymous.id;
+        drop(anonymous);
+        assert!(!disconnect(id));
+        drop(first);
+        drop(second);
+        assert!(list(None, Some(50)).is_empty());
+    }
+}
@@ -39,16 +39,16 @@ impl EventArray {
                 return;
             }
         }
-        if self.segments.len() >= self.max_segments {
-            if let Some(old_seg) = self.segments.pop_front() {
-                let mut seg = old_seg.write().await;
-                seg.pop().await;
-                drop(seg);
-                self.segments.push_back(old_seg);
-                let mut last_seg = self.segments.back().unwrap().write().await;
-                last_seg.push(event).await;
-                return;
-            }
+        if self.segments.len() >= self.max_segments
+            && let Some(old_seg) = self.segments.pop_front()
+        {
+            let mut seg = old_seg.write().await;
+            seg.pop().await;
+            drop(seg);
+            self.segments.push_back(old_seg);
+            let mut last_seg = self.segments.back().unwrap().write().await;
+            last_seg.push(event).await;
+            return;
         }
         let mut new_seg = EventQueue::new(self.segment_capacity);
         new_seg.push(event).await;
@@ -85,6 +85,41 @@ impl EventArray {
         results
     }
 
+    pub async fn query_after(&self, id: u64) -> Vec<Event> {
+        let mut results = Vec::new();
+        if self.segments.is_empty() {
+            return results;
+        }
+        let mut left = 0;
+        let mut right = self.segments.len() - 1;
+        let mut start_index = self.segments.len();
+        while left <= right {
+            let mid = (left + right) / 2;
+            let seg = self.segments[mid].read().await;
+            if seg.is_before_id(id) {
+                left = mid + 1;
+            } else {
+                start_index = mid;
+                if mid == 0 {
+                    break;
+                }
+                right = mid - 1;
+            }
+        }
+        for seg_arc in self.segments.iter().skip(start_index) {
+            let seg = seg_arc.read().await;
+            results.extend(seg.get_events_after(id).await);
+        }
+        results
+    }
+
+    pub async fn first_id(&self) -> Option<u64> {
+        match self.segments.front() {
+            Some(seg) => seg.read().await.first_id().await,
+            None => None,
+        }
+    }
+
     pub async fn query_all(&self) -> Vec<Event> {
         let mut results = Vec::new();
         for seg_arc in &self.segments {
@@ -95,6 +130,29 @@ impl EventArray {
         results
     }
 
+    // Allocated segments and the events they hold.
+    pub async fn occupancy(&self) -> (usize, usize) {
+        let mut events = 0;
+        for seg_arc in &self.segments {
+            let seg = seg_arc.read().await;
+            events += seg.events.read().await.len();
+        }
+        (self.segments.len(), events)
+    }
+
+    pub fn capacity(&self) -> (usize, usize) {
+        (self.segment_capacity, self.max_segments)
+    }
+
+    pub async fn segment_events(&self) -> Vec<Vec<Event>> {
+        let mut results = Vec::with_capacity(self.segments.len());
+        for seg_arc in &self.segments {
+            let seg = seg_arc.read().await;
+            results.push(seg.get_events(None).await);
+        }
+        results
+    }
+
     pub async fn flush_all(&mut self) {
         for seg_arc in &self.segments {
             let mut seg = seg_arc.write().await;
@@ -107,6 +165,7 @@ impl EventArray {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::utils::events::{audience::Audience, topic::default_topic};
     use chrono::Duration;
 
     #[tokio::test]
@@ -114,19 +173,27 @@ mod tests {
         let mut arr = EventArray::new(2, 3);
         let now = Utc::now();
         let e1 = Event {
+            id: 1,
             timestamp: now,
             payload: "E1".into(),
+            audience: Audience::default(),
+            topic: default_topic(),
+            source_key: None,
         };
         let e2 = Event {
+            id: 2,
             timestamp: now,
             payload: "E2".into(),
+            audience: Audience::default(),
+            topic: default_topic(),
+            source_key: None,
         };
         arr.append(e1.clone()).await;
         arr.append(e2.clone()).await;
         let all = arr.query_all().await;
         assert_eq!(all.len(), 2);
-        assert_eq!(all[0].payload, "E1");
-        assert_eq!(all[1].payload, "E2");
+        assert_eq!(all[0].payload.body.as_deref(), Some("E1"));
+        assert_eq!(all[1].payload.body.as_deref(), Some("E2"));
     }
 
     #[tokio::test]
@@ -134,8 +201,12 @@ mod tests {
         let mut arr = EventArray::new(2, 2);
         for i in 1..=5 {
             arr.append(Event {
+                id: i,
                 timestamp: Utc::now(),
-                payload: format!("E{}", i),
+                payload: format!("E{}", i).into(),
+                audience: Audience::default(),
+                topic: default_topic(),
+                source_key: None,
             })
             .await;
         }
@@ -144,7 +215,7 @@ mod tests {
             .query_all()
             .await
             .into_iter()
-            .map(|e| e.payload)
+            .map(|e| e.payload.body.unwrap())
             .collect();
         assert_eq!(payloads, vec!["E3", "E4", "E5"]);
     }
@@ -155,31 +226,70 @@ mod tests {
         let now = Utc::now();
         let events = vec![
             Event {
+                id: 4,
                 timestamp: now,
                 payload: "E1".into(),
+                audience: Audience::default(),
+                topic: default_topic(),
+                source_key: None,
             },
             Event {
+                id: 5,
                 timestamp: now + Duration::seconds(10),
                 payload: "E2".into(),
+                audience: Audience::default(),
+                topic: default_topic(),
+                source_key: None,
             },
             Event {
+                id: 6,
                 timestamp: now + Duration::seconds(20),
                 payload: "E3".into(),
+                audience: Audience::default(),
+                topic: default_topic(),
+                source_key: None,
             },
         ];
         for e in &events {
             arr.append(e.clone()).await;
         }
         let results = arr.query_since(now + Duration::seconds(10)).await;
-        let payloads: Vec<_> = results.into_iter().map(|e| e.payload).collect();
+        let payloads: Vec<_> = results
+            .into_iter()
+            .map(|e| e.payload.body.unwrap())
+            .collect();
         assert_eq!(payloads, vec!["E2", "E3"]);
     }
 
+    #[tokio::test]
+    async fn test_query_after_id() {
+        let mut arr = EventArray::new(2, 2);
+        for i in 1..=5 {
+            arr.append(Event {
+                id: i,
+                timestamp: Utc::now(),
+                payload: format!("E{}", i).into(),
+                audience: Audience::default(),
+                topic: default_topic(),
+                source_key: None,
+            })
+            .await;
+        }
+        assert_eq!(arr.first_id().await, Some(3));
+        let ids: Vec<_> = arr.query_after(3).await.into_iter().map(|e| e.id).collect();
+        assert_eq!(ids, vec![4, 5]);
+        let ids: Vec<_> = arr.query_after(0).await.into_iter().map(|e| e.id).collect();
+        assert_eq!(ids, vec![3, 4, 5]);
+        assert!(arr.query_after(5).await.is_empty());
+    }
+
     #[tokio::test]
     async fn test_empty_query() {
         let arr = EventArray::new(2, 2);
         assert!(arr.query_all().await.is_empty());
         assert!(arr.query_since(Utc::now()).await.is_empty());
+        assert!(arr.query_after(0).await.is_empty());
+        assert!(arr.first_id().await.is_none());
     }
 
     #[tokio::test]
@@ -188,8 +298,12 @@ mod tests {
         let now = Utc::now();
         for i in 1..=3 {
             arr.append(Event {
+                id: i,
                 timestamp: now,
-                payload: format!("Flush{}", i),
+                payload: format!("Flush{}", i).into(),
+                audience: Audience::default(),
+                topic: default_topic(),
+                source_key: None,
             })
             .await;
         }
@@ -205,8 +319,12 @@ mod tests {
         let mut arr = EventArray::new(2, 3);
         for i in 1..=6 {
             arr.append(Event {
+                id: i,
                 timestamp: Utc::now(),
-                payload: format!("E{}", i),
+                payload: format!("E{}", i).into(),
+                audience: Audience::default(),
+                topic: default_topic(),
+                source_key: None,
             })
             .await;
         }
@@ -215,7 +333,7 @@ mod tests {
             .query_all()
             .await
             .into_iter()
-            .map(|e| e.payload)
+            .map(|e| e.payload.body.unwrap())
             .collect();
         assert_eq!(payloads, vec!["E1", "E2", "E3", "E4", "E5", "E6"]);
     }
@@ -225,17 +343,28 @@ mod tests {
         let mut arr = EventArray::new(2, 2);
         let now = Utc::now();
         arr.append(Event {
+            id: 9,
             timestamp: now + Duration::seconds(10),
             payload: "E1".into(),
+            audience: Audience::default(),
+            topic: default_topic(),
+            source_key: None,
         })
         .await;
         arr.append(Event {
+            id: 10,
             timestamp: now + Duration::seconds(20),
             payload: "E2".into(),
+            audience: Audience::default(),
+            topic: default_topic(),
+            source_key: None,
         })
         .await;
         let results = arr.query_since(now).await;
-        let payloads: Vec<_> = results.into_iter().map(|e| e.payload).collect();
+        let payloads: Vec<_> = results
+            .into_iter()
+            .map(|e| e.payload.body.unwrap())
+            .collect();
         assert_eq!(payloads, vec!["E1", "E2"]);
     }
 
@@ -244,13 +373,21 @@ mod tests {
         let mut arr = EventArray::new(2, 2);
         let now = Utc::now();
         arr.append(Event {
+            id: 11,
             timestamp: now,
             payload: "E1".into(),
+            audience: Audience::default(),
+            topic: default_topic(),
+            source_key: None,
         })
         .await;
         arr.append(Event {
+            id: 12,
             timestamp: now + Duration::seconds(10),
             payload: "E2".into(),
+            audience: Audience::default(),
+            topic: default_topic(),
+            source_key: None,
         })
         .await;
         let results = arr.query_since(now + Duration::seconds(20)).await;
@@ -0,0 +1,199 @@
+// Copyright (c) 2025 Intraware
+// Licensed under the MIT License
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     https://opensource.org/licenses/MIT
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use crate::utils::auth::Claims;
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Default, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(try_from = "RawAudience")]
+pub struct Audience {
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub users: Vec<u64>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub teams: Vec<u64>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub roles: Vec<String>,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct Targets {
+    #[serde(default)]
+    users: Vec<u64>,
+    #[serde(default)]
+    teams: Vec<u64>,
+    #[serde(default)]
+    roles: Vec<String>,
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum RawAudience {
+    Keyword(String),
+    Targets(Targets),
+}
+
+// A misspelt key or an object naming nobody would otherwise come out as a
+// broadcast, so only the "broadcast" keyword or a missing audience is one.
+impl TryFrom<RawAudience> for Audience {
+    type Error = String;
+
+    fn try_from(raw: RawAudience) -> Result<Self, Self::Error> {
+        match raw {
+            RawAudience::Keyword(keyword) if keyword == "broadcast" => Ok(Audience::default()),
+            RawAudience::Keyword(keyword) => Err(format!("unknown audience '{}'", keyword)),
+            RawAudience::Targets(t)
+                if t.users.is_empty() && t.teams.is_empty() && t.roles.is_empty() =>
+            {
+                Err(
+                    "audience must name users, teams or roles; use \"broadcast\" for everyone"
+                        .into(),
+                )
+            }
+            RawAudience::Targets(t) if t.roles.iter().any(|r| r.trim().is_empty()) => {
+                Err("audience roles cannot be empty".into())
+            }
+            RawAudience::Targets(t) => Ok(Audience {
+                users: t.users,
+                teams: t.teams,
+                roles: t.roles,
+            }),
+        }
+    }
+}
+
+// Stored events write a broadcast audience as `{}`, which ingest now rejects,
+// so the WAL, snapshots and the event log read it back through this instead.
+pub fn deserialize_stored<'de, D>(deserializer: D) -> Result<Audience, D::Error>
+where
+    D: serde::Deserializer<'de>,
+{
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum Stored {
+        Keyword(String),
+        Targets {
+            #[serde(default)]
+            users: Vec<u64>,
+            #[serde(default)]
+            teams: Vec<u64>,
+            #[serde(default)]
+            roles: Vec<String>,
+        },
+    }
+    match Stored::deserialize(deserializer)? {
+        Stored::Keyword(keyword) if keyword == "broadcast" => Ok(Audience::default()),
+        Stored::Keyword(keyword) => Err(serde::de::Error::custom(format!(
+            "unknown audience '{}'",
+            keyword
+        ))),
+        Stored::Targets {
+            users,
+            teams,
+            roles,
+        } => Ok(Audience {
+            users,
+            teams,
+            roles,
+        }),
+    }
+}
+
+impl Audience {
+    pub fn is_broadcast(&self) -> bool {
+        self.users.is_empty() && self.teams.is_empty() && self.roles.is_empty()
+    }
+
+    // Roles narrow the audience: with `roles` set, a subscriber needs one of
+    // them and, if users or teams are also named, must be among those too.
+    pub fn matches(&self, claims: Option<&Claims>) -> bool {
+        if self.is_broadcast() {
+            return true;
+        }
+        let Some(claims) = claims else {
+            return false;
+        };
+        if !self.roles.is_empty() && !self.roles.iter().any(|r| claims.has_role(r)) {
+            return false;
+        }
+        (self.users.is_empty() && self.teams.is_empty())
+            || self.users.contains(&claims.user_id)
+            || self.teams.contains(&claims.team_id)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_broadcast_matches_everyone() {
+        let audience = Audience::default();
+        assert!(audience.is_broadcast());
+        assert!(audience.matches(None));
+        assert!(audience.matches(Some(&Claims::for_test(1, 1))));
+        let keyword: Audience = serde_json::from_value(json!("broadcast")).unwrap();
+        assert!(keyword.is_broadcast());
+    }
+
+    #[test]
+    fn test_targeted_audience() {
+        let audience: Audience =
+            serde_json::from_value(json!({"users": [7], "teams": [3]})).unwrap();
+        assert!(!audience.is_broadcast());
+        assert!(audience.matches(Some(&Claims::for_test(7, 1))));
+        assert!(audience.matches(Some(&Claims::for_test(2, 3))));
+        assert!(!audience.matches(Some(&Claims::for_test(2, 4))));
+        assert!(!audience.matches(None));
+    }
+
+    #[test]
+    fn test_role_audience() {
+        let mut organizer = Claims::for_test(1, 0);
+        organizer.roles = vec!["admin".into()];
+        let admins: Audience = serde_json::from_value(json!({"roles": ["admin"]})).unwrap();
+        assert!(!admins.is_broadcast());
+        assert!(admins.matches(Some(&organizer)));
+        assert!(!admins.matches(Some(&Claims::for_test(2, 3))));
+        assert!(!admins.matches(None));
+        let admins_of_team: Audience =
+            serde_json::from_value(json!({"roles": ["admin"], "teams": [3]})).unwrap();
+        assert!(!admins_of_team.matches(Some(&organizer)));
+        assert!(!admins_of_team.matches(Some(&Claims::for_test(2, 3))));
+        assert!(serde_json::from_value::<Audience>(json!({"roles": [""]})).is_err());
+    }
+
+    #[test]
+    fn test_unknown_keys_rejected() {
+        assert!(serde_json::from_value::<Audience>(json!({"user": [7]})).is_err());
+        assert!(serde_json::from_value::<Audience>(json!({"teams": [3], "team": [4]})).is_err());
+    }
+
+    #[test]
+    fn test_empty_targets_rejected() {
+        assert!(serde_json::from_value::<Audience>(json!({})).is_err());
+        assert!(
+            serde_json::from_value::<Audience>(json!({"users": [], "teams": [], "roles": []}))
+                .is_err()
+        );
+        // Broadcasts already stored as `{}` still load.
+        let stored = deserialize_stored(json!({})).unwrap();
+        assert!(stored.is_broadcast());
+    }
+
+    #[test]
+    fn test_unknown_keyword_rejected() {
+        assert!(serde_json::from_value::<Audience>(json!("everyone")).is_err());
+    }
+}
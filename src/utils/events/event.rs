@@ -12,22 +12,100 @@
 // limitations under the License.
 
 use crate::{
-    utils::events::logging::{GLOBAL_LOG, Log},
+    utils::events::{
+        audience::Audience,
+        logging::{GLOBAL_LOG, Log},
+        notification::{IncomingNotification, Notification},
+        topic::{default_topic, validate_topic},
+    },
     values::config::get_config,
 };
 use chrono::{DateTime, Utc};
 use std::sync::Arc;
 use tokio::sync::RwLock;
 
-#[derive(Clone)]
+#[derive(Clone, Debug, serde::Serialize, serde::Deserialize)]
 pub struct Event {
+    pub id: u64,
     pub timestamp: DateTime<Utc>,
-    pub payload: String,
+    pub payload: Notification,
+    #[serde(deserialize_with = "super::audience::deserialize_stored")]
+    pub audience: Audience,
+    pub topic: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub source_key: Option<String>,
+}
+
+// A plain "E<id>" message stamped now, for tests across the crate.
+#[cfg(test)]
+impl Event {
+    pub fn for_test(id: u64) -> Event {
+        Event {
+            id,
+            timestamp: Utc::now(),
+            payload: Notification::from(format!("E{}", id)),
+            audience: Audience::default(),
+            topic: default_topic(),
+            source_key: None,
+        }
+    }
+}
+
+pub struct EventDraft {
+    pub payload: Notification,
+    pub audience: Audience,
+    pub topic: String,
+    pub source_key: Option<String>,
+}
+
+impl EventDraft {
+    pub fn validate(&self) -> Result<(), String> {
+        self.payload.validate()?;
+        validate_topic(&self.topic)
+    }
+}
+
+impl From<Notification> for EventDraft {
+    fn from(payload: Notification) -> Self {
+        EventDraft {
+            payload,
+            audience: Audience::default(),
+            topic: default_topic(),
+            source_key: None,
+        }
+    }
+}
+
+impl From<&str> for EventDraft {
+    fn from(body: &str) -> Self {
+        EventDraft::from(Notification::from(body))
+    }
+}
+
+impl From<String> for EventDraft {
+    fn from(body: String) -> Self {
+        EventDraft::from(Notification::from(body))
+    }
+}
+
+impl From<IncomingNotification> for EventDraft {
+    fn from(incoming: IncomingNotification) -> Self {
+        match incoming {
+            IncomingNotification::Plain(body) => EventDraft::from(body),
+            IncomingNotification::Envelope(envelope) => EventDraft {
+                payload: envelope.notification,
+                audience: envelope.audience,
+                topic: envelope.topic,
+                source_key: None,
+            },
+        }
+    }
 }
 
 pub struct EventQueue {
     start_timestamp: Option<DateTime<Utc>>,
     end_timestamp: Option<DateTime<Utc>>,
+    end_id: Option<u64>,
     pub events: RwLock<Vec<Event>>,
     pub capacity: usize,
     pub log: Option<Arc<Log>>,
@@ -38,6 +116,7 @@ impl EventQueue {
         Self {
             start_timestamp: None,
             end_timestamp: None,
+            end_id: None,
             events: RwLock::new(Vec::with_capacity(capacity)),
             capacity,
             log: if get_config().app.event_logging {
@@ -56,6 +135,7 @@ impl EventQueue {
             self.start_timestamp = Some(event.timestamp);
         }
         self.end_timestamp = Some(event.timestamp);
+        self.end_id = Some(event.id);
         let mut events = self.events.write().await;
         events.push(event);
         true
@@ -72,7 +152,7 @@ impl EventQueue {
         if let Some(log) = &self.log {
             let mut events = self.events.write().await;
             for event in events.drain(..) {
-                log.add_event(event.payload).await;
+                log.add_event(event).await;
             }
             let log_file = get_config().app.events_logfile.clone().unwrap();
             log.write_events(log_file).await;
@@ -87,6 +167,7 @@ impl EventQueue {
     pub async fn reset(&mut self) {
         self.start_timestamp = None;
         self.end_timestamp = None;
+        self.end_id = None;
         let mut events = self.events.write().await;
         events.clear();
     }
@@ -98,6 +179,23 @@ impl EventQueue {
         }
     }
 
+    pub fn is_before_id(&self, id: u64) -> bool {
+        match self.end_id {
+            Some(end) => end <= id,
+            None => true,
+        }
+    }
+
+    pub async fn first_id(&self) -> Option<u64> {
+        let events = self.events.read().await;
+        events.first().map(|e| e.id)
+    }
+
+    pub async fn get_events_after(&self, id: u64) -> Vec<Event> {
+        let events = self.events.read().await;
+        events.iter().filter(|e| e.id > id).cloned().collect()
+    }
+
     pub async fn get_events(&self, time: Option<DateTime<Utc>>) -> Vec<Event> {
         let events = self.events.read().await;
         if let Some(t) = time {
@@ -122,12 +220,20 @@ mod tests {
         let mut queue = EventQueue::new(2);
         assert!(!queue.is_full().await);
         let event1 = Event {
+            id: 1,
             timestamp: Utc::now(),
             payload: "Event 1".into(),
+            audience: Audience::default(),
+            topic: default_topic(),
+            source_key: None,
         };
         let event2 = Event {
+            id: 2,
             timestamp: Utc::now(),
             payload: "Event 2".into(),
+            audience: Audience::default(),
+            topic: default_topic(),
+            source_key: None,
         };
         assert!(queue.push(event1).await);
         assert!(!queue.is_full().await);
@@ -140,8 +246,12 @@ mod tests {
         let mut queue = EventQueue::new(2);
         queue
             .push(Event {
+                id: 3,
                 timestamp: Utc::now(),
                 payload: "Event 1".into(),
+                audience: Audience::default(),
+                topic: default_topic(),
+                source_key: None,
             })
             .await;
         queue.reset().await;
@@ -149,6 +259,34 @@ mod tests {
         assert!(events.is_empty());
         assert!(queue.start_timestamp.is_none());
         assert!(queue.end_timestamp.is_none());
+        assert!(queue.end_id.is_none());
+    }
+
+    #[tokio::test]
+    async fn test_event_queue_get_events_after() {
+        let mut queue = EventQueue::new(3);
+        for id in 1..=3 {
+            queue
+                .push(Event {
+                    id,
+                    timestamp: Utc::now(),
+                    payload: format!("Event {}", id).into(),
+                    audience: Audience::default(),
+                    topic: default_topic(),
+                    source_key: None,
+                })
+                .await;
+        }
+        assert_eq!(queue.first_id().await, Some(1));
+        assert!(queue.is_before_id(3));
+        assert!(!queue.is_before_id(2));
+        let ids: Vec<_> = queue
+            .get_events_after(1)
+            .await
+            .into_iter()
+            .map(|e| e.id)
+            .collect();
+        assert_eq!(ids, vec![2, 3]);
     }
 
     #[tokio::test]
@@ -156,14 +294,22 @@ mod tests {
         let mut queue = EventQueue::new(2);
         queue
             .push(Event {
+                id: 4,
                 timestamp: Utc::now(),
                 payload: "Flush Event 1".into(),
+                audience: Audience::default(),
+                topic: default_topic(),
+                source_key: None,
             })
             .await;
         queue
             .push(Event {
+                id: 5,
                 timestamp: Utc::now(),
                 payload: "Flush Event 2".into(),
+                audience: Audience::default(),
+                topic: default_topic(),
+                source_key: None,
             })
             .await;
         queue.pop().await;
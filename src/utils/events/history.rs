@@ -0,0 +1,217 @@
+// Copyright (c) 2025 Intraware
+// Licensed under the MIT License
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     https://opensource.org/licenses/MIT
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use crate::utils::events::{event::Event, logging::LogEvent};
+use chrono::{DateTime, NaiveDateTime, Utc};
+use std::{io, path::Path};
+use tokio::{
+    fs,
+    io::{AsyncBufReadExt, BufReader},
+};
+
+// Rotated files are named `<log file>.<%Y%m%d%H%M%S>` by `rotate_logs`; the
+// suffix is the time of rotation, so every event in the file is older.
+fn rotated_at(log_name: &str, file_name: &str) -> Option<DateTime<Utc>> {
+    let suffix = file_name.strip_prefix(log_name)?.strip_prefix('.')?;
+    NaiveDateTime::parse_from_str(suffix, "%Y%m%d%H%M%S")
+        .ok()
+        .map(|t| t.and_utc())
+}
+
+async fn log_files(path: &str, from: Option<DateTime<Utc>>) -> io::Result<Vec<String>> {
+    let log_path = Path::new(path);
+    let log_name = match log_path.file_name().and_then(|n| n.to_str()) {
+        Some(n) => n,
+        None => return Ok(Vec::new()),
+    };
+    let dir = match log_path.parent() {
+        Some(p) if !p.as_os_str().is_empty() => p,
+        _ => Path::new("."),
+    };
+    let mut rotated = Vec::new();
+    let mut entries = match fs::read_dir(dir).await {
+        Ok(entries) => entries,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(e) => return Err(e),
+    };
+    while let Some(entry) = entries.next_entry().await? {
+        let file_name = entry.file_name();
+        let Some(file_name) = file_name.to_str() else {
+            continue;
+        };
+        if let Some(at) = rotated_at(log_name, file_name)
+            && from.is_none_or(|from| at >= from)
+        {
+            rotated.push((at, entry.path().to_string_lossy().into_owned()));
+        }
+    }
+    rotated.sort();
+    let mut files: Vec<String> = rotated.into_iter().map(|(_, p)| p).collect();
+    files.push(path.to_string());
+    Ok(files)
+}
+
+// Files are read line by line, oldest first, and reading stops once `max`
+// events in range have passed `keep`.
+pub async fn read_log_history(
+    path: &str,
+    from: Option<DateTime<Utc>>,
+    to: Option<DateTime<Utc>>,
+    keep: impl Fn(&Event) -> bool,
+    max: usize,
+) -> io::Result<Vec<Event>> {
+    let mut events = Vec::new();
+    for file in log_files(path, from).await? {
+        let file = match fs::File::open(&file).await {
+            Ok(f) => f,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => continue,
+            Err(e) => return Err(e),
+        };
+        let mut lines = BufReader::new(file).lines();
+        while let Some(line) = lines.next_line().await? {
+            // Lines that do not parse, e.g. the `{}` written when serialisation
+            // fails, are skipped rather than failing the whole query.
+            let Some(event) = serde_json::from_str::<LogEvent>(&line)
+                .ok()
+                .and_then(|e| Event::try_from(e).ok())
+            else {
+                continue;
+            };
+            if from.is_none_or(|from| event.timestamp >= from)
+                && to.is_none_or(|to| event.timestamp < to)
+                && keep(&event)
+            {
+                events.push(event);
+                if events.len() >= max {
+                    return Ok(events);
+                }
+            }
+        }
+    }
+    Ok(events)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::events::logging::Log;
+    use chrono::Duration;
+
+    fn temp_dir() -> std::path::PathBuf {
+        std::env::temp_dir().join(format!("rodan-history-test-{}", rand::random::<u64>()))
+    }
+
+    fn event(id: u64, timestamp: DateTime<Utc>) -> Event {
+        Event {
+            timestamp,
+            ..Event::for_test(id)
+        }
+    }
+
+    #[test]
+    fn test_rotated_at() {
+        assert_eq!(
+            rotated_at("events.log", "events.log.20250102030405"),
+            Some(
+                NaiveDateTime::parse_from_str("20250102030405", "%Y%m%d%H%M%S")
+                    .unwrap()
+                    .and_utc()
+            )
+        );
+        assert_eq!(rotated_at("events.log", "events.log"), None);
+        assert_eq!(rotated_at("events.log", "events.log.tmp"), None);
+        assert_eq!(rotated_at("events.log", "other.log.20250102030405"), None);
+    }
+
+    #[tokio::test]
+    async fn test_reads_active_and_rotated_files() {
+        let dir = temp_dir();
+        fs::create_dir_all(&dir).await.unwrap();
+        let path = dir.join("events.log").to_string_lossy().into_owned();
+        let now = Utc::now();
+        let log = Log::new();
+        log.add_event(event(1, now - Duration::hours(3))).await;
+        log.add_event(event(2, now - Duration::hours(2))).await;
+        log.write_events(path.clone()).await;
+        let rotated = format!(
+            "{}.{}",
+            path,
+            (now - Duration::minutes(90)).format("%Y%m%d%H%M%S")
+        );
+        fs::rename(&path, &rotated).await.unwrap();
+        log.add_event(event(3, now - Duration::hours(1))).await;
+        log.write_events(path.clone()).await;
+        fs::write(format!("{}.bak", path), "ignored").await.unwrap();
+
+        let all = read_log_history(&path, None, None, |_| true, usize::MAX)
+            .await
+            .unwrap();
+        assert_eq!(all.iter().map(|e| e.id).collect::<Vec<_>>(), vec![1, 2, 3]);
+        assert_eq!(all[0].payload.body.as_deref(), Some("E1"));
+        let first = read_log_history(&path, None, None, |e| e.id != 1, 1)
+            .await
+            .unwrap();
+        assert_eq!(first.iter().map(|e| e.id).collect::<Vec<_>>(), vec![2]);
+
+        let ranged = read_log_history(
+            &path,
+            Some(now - Duration::minutes(150)),
+            Some(now - Duration::minutes(30)),
+            |_| true,
+            usize::MAX,
+        )
+        .await
+        .unwrap();
+        assert_eq!(ranged.iter().map(|e| e.id).collect::<Vec<_>>(), vec![2, 3]);
+
+        let recent = read_log_history(
+            &path,
+            Some(now - Duration::minutes(80)),
+            None,
+            |_| true,
+            usize::MAX,
+        )
+        .await
+        .unwrap();
+        assert_eq!(recent.iter().map(|e| e.id).collect::<Vec<_>>(), vec![3]);
+        fs::remove_dir_all(&dir).await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_reads_pre_envelope_lines() {
+        let dir = temp_dir();
+        fs::create_dir_all(&dir).await.unwrap();
+        let path = dir.join("events.log").to_string_lossy().into_owned();
+        let line = r#"{"timestamp":"2025-01-02T03:04:05+00:00","level":"INFO","target":"rodan.events","message":"user_logged_in","type":"notifications"}"#;
+        fs::write(&path, format!("{}\n", line)).await.unwrap();
+        let events = read_log_history(&path, None, None, |_| true, usize::MAX)
+            .await
+            .unwrap();
+        assert_eq!(events.len(), 1);
+        assert_eq!(events[0].id, 0);
+        assert_eq!(events[0].payload.kind, "message");
+        assert_eq!(events[0].payload.body.as_deref(), Some("user_logged_in"));
+        fs::remove_dir_all(&dir).await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_missing_log_is_empty() {
+        let path = temp_dir().join("events.log").to_string_lossy().into_owned();
+        assert!(
+            read_log_history(&path, None, None, |_| true, usize::MAX)
+                .await
+                .unwrap()
+                .is_empty()
+        );
+    }
+}
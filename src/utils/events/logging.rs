@@ -11,7 +11,11 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
-use chrono::Utc;
+use crate::utils::events::{
+    audience::Audience, event::Event, notification::Notification, topic::default_topic,
+};
+use crate::utils::metrics;
+use chrono::{DateTime, Utc};
 use once_cell::sync::Lazy;
 use std::{path::Path, sync::Arc};
 use tokio::io::AsyncWriteExt;
@@ -20,26 +24,76 @@ use tokio::{
     sync::RwLock,
 };
 
-#[derive(Debug, Clone, serde::Serialize)]
-struct LogEvent {
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct LogEvent {
+    #[serde(default)]
+    id: u64,
     timestamp: String,
     level: String,
     target: String,
-    message: String,
+    #[serde(deserialize_with = "deserialize_message")]
+    message: Notification,
+    #[serde(
+        default,
+        skip_serializing_if = "Audience::is_broadcast",
+        deserialize_with = "super::audience::deserialize_stored"
+    )]
+    audience: Audience,
+    #[serde(default = "default_topic")]
+    topic: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    source_key: Option<String>,
     #[serde(rename = "type")]
     log_type: String,
 }
 
-fn log_event(message: String) -> LogEvent {
+// Lines written before the envelope carry the message as a plain string, and
+// no id.
+fn deserialize_message<'de, D>(deserializer: D) -> Result<Notification, D::Error>
+where
+    D: serde::Deserializer<'de>,
+{
+    #[derive(serde::Deserialize)]
+    #[serde(untagged)]
+    enum Logged {
+        Plain(String),
+        Envelope(Notification),
+    }
+    Ok(match serde::Deserialize::deserialize(deserializer)? {
+        Logged::Plain(body) => Notification::from(body),
+        Logged::Envelope(notification) => notification,
+    })
+}
+
+fn log_event(event: Event) -> LogEvent {
     LogEvent {
-        timestamp: Utc::now().to_rfc3339(),
+        id: event.id,
+        timestamp: event.timestamp.to_rfc3339(),
         level: "INFO".into(),
         target: "rodan.events".into(),
-        message: message,
+        message: event.payload,
+        audience: event.audience,
+        topic: event.topic,
+        source_key: event.source_key,
         log_type: "notifications".into(),
     }
 }
 
+impl TryFrom<LogEvent> for Event {
+    type Error = chrono::ParseError;
+
+    fn try_from(log_event: LogEvent) -> Result<Self, Self::Error> {
+        Ok(Event {
+            id: log_event.id,
+            timestamp: DateTime::parse_from_rfc3339(&log_event.timestamp)?.with_timezone(&Utc),
+            payload: log_event.message,
+            audience: log_event.audience,
+            topic: log_event.topic,
+            source_key: log_event.source_key,
+        })
+    }
+}
+
 pub struct Log {
     events: RwLock<Vec<LogEvent>>,
 }
@@ -51,9 +105,9 @@ impl Log {
         }
     }
 
-    pub async fn add_event(&self, message: String) {
+    pub async fn add_event(&self, event: Event) {
         let mut events = self.events.write().await;
-        events.push(log_event(message));
+        events.push(log_event(event));
     }
 
     pub async fn write_events(&self, path: String) {
@@ -69,11 +123,12 @@ impl Log {
             + "\n";
         events.clear();
         drop(events);
-        if let Some(parent) = Path::new(&path).parent() {
-            if let Err(e) = fs::create_dir_all(parent).await {
-                eprintln!("Failed to create log directory: {}", e);
-                return;
-            }
+        if let Some(parent) = Path::new(&path).parent()
+            && let Err(e) = fs::create_dir_all(parent).await
+        {
+            eprintln!("Failed to create log directory: {}", e);
+            metrics::record_log_write_failure();
+            return;
         }
         match OpenOptions::new()
             .create(true)
@@ -84,9 +139,13 @@ impl Log {
             Ok(mut file) => {
                 if let Err(e) = file.write_all(serialized.as_bytes()).await {
                     eprintln!("Failed to write events to log file: {}", e);
+                    metrics::record_log_write_failure();
                 }
             }
-            Err(e) => eprintln!("Failed to open log file: {}", e),
+            Err(e) => {
+                eprintln!("Failed to open log file: {}", e);
+                metrics::record_log_write_failure();
+            }
         }
     }
 }
@@ -98,17 +157,25 @@ mod tests {
     use super::*;
     use serial_test::serial;
 
+    fn event(id: u64, message: &str) -> Event {
+        Event {
+            payload: message.into(),
+            ..Event::for_test(id)
+        }
+    }
+
     #[tokio::test]
     async fn test_add_event() {
         let log = Log::new();
 
-        log.add_event("Test message 1".into()).await;
-        log.add_event("Test message 2".into()).await;
+        log.add_event(event(1, "Test message 1")).await;
+        log.add_event(event(2, "Test message 2")).await;
 
         let events = log.events.read().await;
         assert_eq!(events.len(), 2);
-        assert_eq!(events[0].message, "Test message 1");
-        assert_eq!(events[1].message, "Test message 2");
+        assert_eq!(events[0].id, 1);
+        assert_eq!(events[0].message.body.as_deref(), Some("Test message 1"));
+        assert_eq!(events[1].message.body.as_deref(), Some("Test message 2"));
     }
 
     #[tokio::test]
@@ -117,19 +184,19 @@ mod tests {
         let mut events = GLOBAL_LOG.events.write().await;
         events.clear();
         drop(events);
-        GLOBAL_LOG.add_event("Global event 1".into()).await;
-        GLOBAL_LOG.add_event("Global event 2".into()).await;
+        GLOBAL_LOG.add_event(event(3, "Global event 1")).await;
+        GLOBAL_LOG.add_event(event(4, "Global event 2")).await;
         let events = GLOBAL_LOG.events.read().await;
         assert_eq!(events.len(), 2);
-        assert_eq!(events[0].message, "Global event 1");
-        assert_eq!(events[1].message, "Global event 2");
+        assert_eq!(events[0].message.body.as_deref(), Some("Global event 1"));
+        assert_eq!(events[1].message.body.as_deref(), Some("Global event 2"));
     }
 
     #[tokio::test]
     async fn test_flush_events_clears_log() {
         let log = Log::new();
-        log.add_event("Flush test 1".into()).await;
-        log.add_event("Flush test 2".into()).await;
+        log.add_event(event(5, "Flush test 1")).await;
+        log.add_event(event(6, "Flush test 2")).await;
         log.write_events("events.log".into()).await;
         let events_after = log.events.read().await;
         assert!(events_after.is_empty());
@@ -141,8 +208,8 @@ mod tests {
         let mut events = GLOBAL_LOG.events.write().await;
         events.clear();
         drop(events);
-        GLOBAL_LOG.add_event("Global Flush test 1".into()).await;
-        GLOBAL_LOG.add_event("Global Flush test 2".into()).await;
+        GLOBAL_LOG.add_event(event(7, "Global Flush test 1")).await;
+        GLOBAL_LOG.add_event(event(8, "Global Flush test 2")).await;
         GLOBAL_LOG.write_events("events.log".into()).await;
         let events_after = GLOBAL_LOG.events.read().await;
         assert!(events_after.is_empty());
@@ -12,17 +12,37 @@
 // limitations under the License.
 
 mod array;
+mod audience;
 mod event;
+mod history;
 mod logging;
-pub use event::Event;
+mod notification;
+mod snapshot;
+mod topic;
+mod wal;
+pub use audience::Audience;
+pub use event::{Event, EventDraft};
+pub use notification::{IncomingNotification, Notification, Severity};
+pub use topic::{DEFAULT_TOPIC, parse_topics};
 
 use array::EventArray;
 use chrono::{DateTime, Utc};
 use once_cell::sync::Lazy;
-use std::sync::Arc;
-use tokio::sync::RwLock;
+use snapshot::Snapshot;
+use std::{
+    sync::{
+        Arc,
+        atomic::{AtomicU64, Ordering},
+    },
+    time::Duration,
+};
+use tokio::sync::{Mutex, OnceCell, RwLock};
+use wal::Wal;
 
-use crate::values::{config::get_config, events::EVENT_CHANNEL};
+use crate::{
+    config::app::WalSync,
+    values::{config::get_config, events},
+};
 
 static GLOBAL_EVENT_ARRAY: Lazy<Arc<RwLock<EventArray>>> = Lazy::new(|| {
     let cfg = get_config();
@@ -31,14 +51,151 @@ static GLOBAL_EVENT_ARRAY: Lazy<Arc<RwLock<EventArray>>> = Lazy::new(|| {
     Arc::new(RwLock::new(EventArray::new(segment_size, max_segments)))
 });
 
-pub async fn push_event(message: String) {
-    let _ = EVENT_CHANNEL.send(message.clone());
+static EVENT_SEQUENCE: AtomicU64 = AtomicU64::new(1);
+
+static GLOBAL_WAL: OnceCell<Wal> = OnceCell::const_new();
+
+static PUBLISH_LOCK: Mutex<()> = Mutex::const_new(());
+
+pub fn last_event_id() -> u64 {
+    EVENT_SEQUENCE.load(Ordering::SeqCst) - 1
+}
+
+pub async fn push_event(draft: EventDraft) -> std::io::Result<Event> {
+    // Publishing is serialised so that ids reach the WAL, the channel and the
+    // buffer in the same order; the buffer itself is only locked to append,
+    // so readers do not wait on the WAL.
+    let _publishing = PUBLISH_LOCK.lock().await;
     let event = Event {
+        id: EVENT_SEQUENCE.load(Ordering::SeqCst),
         timestamp: Utc::now(),
-        payload: message,
+        payload: draft.payload,
+        audience: draft.audience,
+        topic: draft.topic,
+        source_key: draft.source_key,
     };
+    if let Some(wal) = GLOBAL_WAL.get() {
+        wal.append(&event).await?;
+    }
+    let mut arr = GLOBAL_EVENT_ARRAY.write().await;
+    EVENT_SEQUENCE.store(event.id + 1, Ordering::SeqCst);
+    events::publish(&event);
+    arr.append(event.clone()).await;
+    let first = arr.first_id().await;
+    drop(arr);
+    // Still under the publish lock, so the inbox sees events in id order.
+    crate::utils::inbox::deliver(&event);
+    if let Some(wal) = GLOBAL_WAL.get()
+        && let Some(first) = first
+        && let Err(e) = wal.compact(first).await
+    {
+        eprintln!("Failed to compact WAL: {}", e);
+    }
+    Ok(event)
+}
+
+// Loads previously persisted events into the buffer, keeping only as many as
+// it can hold so that nothing is evicted (and logged a second time) on the way in.
+// Events the buffer already holds, e.g. from a snapshot, are skipped.
+async fn restore_events(mut restored: Vec<Event>) {
+    let cfg = get_config();
+    let capacity =
+        cfg.app.event_segment_size.unwrap_or(1000) * cfg.app.event_max_segments.unwrap_or(10);
     let mut arr = GLOBAL_EVENT_ARRAY.write().await;
-    arr.append(event).await;
+    if let Some(newest) = arr.query_all().await.last() {
+        restored.retain(|e| e.id > newest.id);
+    }
+    if restored.len() > capacity {
+        restored.drain(..restored.len() - capacity);
+    }
+    if let Some(last) = restored.last() {
+        EVENT_SEQUENCE.fetch_max(last.id + 1, Ordering::SeqCst);
+    }
+    for event in restored {
+        arr.append(event).await;
+    }
+}
+
+pub async fn init_wal() -> std::io::Result<()> {
+    let cfg = get_config();
+    let wal_cfg = match &cfg.app.wal {
+        Some(w) => w,
+        None => return Ok(()),
+    };
+    let segment_size = cfg.app.event_segment_size.unwrap_or(1000);
+    let (wal, recovered) = Wal::open(&wal_cfg.dir, wal_cfg.sync, segment_size).await?;
+    let count = recovered.len();
+    restore_events(recovered).await;
+    if let Some(first) = first_event_id().await {
+        wal.compact(first).await?;
+    }
+    println!("Recovered {} events from the WAL", count);
+    if GLOBAL_WAL.set(wal).is_err() {
+        return Err(std::io::Error::other("WAL is already initialised"));
+    }
+    if wal_cfg.sync == WalSync::Interval {
+        let interval = wal_cfg.sync_interval.unwrap_or(Duration::from_secs(1));
+        tokio::spawn(async move {
+            loop {
+                tokio::time::sleep(interval).await;
+                if let Some(wal) = GLOBAL_WAL.get()
+                    && let Err(e) = wal.sync().await
+                {
+                    eprintln!("Failed to sync WAL: {}", e);
+                }
+            }
+        });
+    }
+    Ok(())
+}
+
+async fn save_snapshot(path: &str) -> Result<usize, String> {
+    let arr = GLOBAL_EVENT_ARRAY.read().await;
+    let snapshot = Snapshot {
+        next_id: EVENT_SEQUENCE.load(Ordering::SeqCst),
+        segments: arr.segment_events().await,
+        cursors: crate::utils::receipts::all_cursors(),
+    };
+    snapshot::write_snapshot(path, &snapshot).await?;
+    Ok(snapshot.segments.iter().map(Vec::len).sum())
+}
+
+async fn load_snapshot(path: &str) -> Result<Option<usize>, String> {
+    let snapshot = match snapshot::read_snapshot(path).await? {
+        Some(s) => s,
+        None => return Ok(None),
+    };
+    let restored: Vec<Event> = snapshot.segments.into_iter().flatten().collect();
+    let count = restored.len();
+    restore_events(restored).await;
+    EVENT_SEQUENCE.fetch_max(snapshot.next_id, Ordering::SeqCst);
+    crate::utils::receipts::restore(snapshot.cursors);
+    Ok(Some(count))
+}
+
+// The snapshot is consumed on load so that a later crash cannot bring back
+// stale history; a rejected one is set aside rather than overwritten.
+pub async fn restore_snapshot() {
+    let path = match &get_config().app.snapshot_file {
+        Some(p) => p.clone(),
+        None => return,
+    };
+    match load_snapshot(&path).await {
+        Ok(Some(count)) => {
+            println!("Restored {} events from snapshot {}", count, path);
+            if let Err(e) = tokio::fs::remove_file(&path).await {
+                eprintln!("Failed to remove snapshot {}: {}", path, e);
+            }
+        }
+        Ok(None) => {}
+        Err(e) => {
+            eprintln!("Rejected snapshot {}: {}", path, e);
+            let rejected = format!("{}.rejected", path);
+            if let Err(e) = tokio::fs::rename(&path, &rejected).await {
+                eprintln!("Failed to move snapshot to {}: {}", rejected, e);
+            }
+        }
+    }
 }
 
 pub async fn flush_events() {
@@ -46,6 +203,30 @@ pub async fn flush_events() {
     arr.flush_all().await;
 }
 
+// With a WAL or a snapshot the buffer is rebuilt on the next start, so it is
+// not drained into the event log, which would log every event twice.
+pub async fn shutdown_events() {
+    let mut persisted = false;
+    if let Some(wal) = GLOBAL_WAL.get() {
+        if let Err(e) = wal.sync().await {
+            eprintln!("Failed to sync WAL: {}", e);
+        }
+        persisted = true;
+    }
+    if let Some(path) = &get_config().app.snapshot_file {
+        match save_snapshot(path).await {
+            Ok(count) => {
+                println!("Saved {} events to snapshot {}", count, path);
+                persisted = true;
+            }
+            Err(e) => eprintln!("Failed to write snapshot {}: {}", path, e),
+        }
+    }
+    if !persisted {
+        flush_events().await;
+    }
+}
+
 pub async fn get_events(time: Option<DateTime<Utc>>) -> Vec<Event> {
     let arr = GLOBAL_EVENT_ARRAY.read().await;
     match time {
@@ -54,6 +235,77 @@ pub async fn get_events(time: Option<DateTime<Utc>>) -> Vec<Event> {
     }
 }
 
+pub async fn get_events_after(id: u64) -> Vec<Event> {
+    let arr = GLOBAL_EVENT_ARRAY.read().await;
+    arr.query_after(id).await
+}
+
+// Evicted events only survive in the event log and its rotations, so history
+// is read from there and completed with whatever the buffer still holds. At
+// most `max` events that pass `keep` are returned, oldest first.
+pub async fn get_history(
+    from: Option<DateTime<Utc>>,
+    to: Option<DateTime<Utc>>,
+    keep: impl Fn(&Event) -> bool,
+    max: usize,
+) -> std::io::Result<Vec<Event>> {
+    let cfg = get_config();
+    let mut events = match &cfg.app.events_logfile {
+        Some(path) if cfg.app.event_logging => {
+            history::read_log_history(path, from, to, &keep, max).await?
+        }
+        _ => Vec::new(),
+    };
+    let remaining = max - events.len();
+    events.extend(
+        get_events(from)
+            .await
+            .into_iter()
+            .filter(|e| to.is_none_or(|to| e.timestamp < to) && keep(e))
+            .take(remaining),
+    );
+    events.sort_by_key(|e| e.timestamp);
+    Ok(events)
+}
+
+pub fn in_topics(event: &Event, topics: &[String]) -> bool {
+    topics.is_empty() || topics.contains(&event.topic)
+}
+
+pub async fn first_event_id() -> Option<u64> {
+    let arr = GLOBAL_EVENT_ARRAY.read().await;
+    arr.first_id().await
+}
+
+pub async fn buffer_responsive(timeout: Duration) -> bool {
+    tokio::time::timeout(timeout, GLOBAL_EVENT_ARRAY.write())
+        .await
+        .is_ok()
+}
+
+pub fn wal_open() -> bool {
+    GLOBAL_WAL.get().is_some()
+}
+
+pub struct BufferStats {
+    pub segments: usize,
+    pub max_segments: usize,
+    pub events: usize,
+    pub capacity: usize,
+}
+
+pub async fn buffer_stats() -> BufferStats {
+    let arr = GLOBAL_EVENT_ARRAY.read().await;
+    let (segments, events) = arr.occupancy().await;
+    let (segment_capacity, max_segments) = arr.capacity();
+    BufferStats {
+        segments,
+        max_segments,
+        events,
+        capacity: segment_capacity * max_segments,
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -70,11 +322,14 @@ mod tests {
     #[serial]
     async fn test_push_event_adds_to_global_array() {
         reset_global_array().await;
-        push_event("Event A".into()).await;
-        push_event("Event B".into()).await;
+        push_event("Event A".into()).await.unwrap();
+        push_event("Event B".into()).await.unwrap();
         let arr = GLOBAL_EVENT_ARRAY.read().await;
         let all_events = arr.query_all().await;
-        let payloads: Vec<_> = all_events.iter().map(|e| e.payload.clone()).collect();
+        let payloads: Vec<_> = all_events
+            .iter()
+            .map(|e| e.payload.body.clone().unwrap())
+            .collect();
         assert_eq!(payloads, vec!["Event A", "Event B"]);
     }
 
@@ -82,8 +337,8 @@ mod tests {
     #[serial]
     async fn test_flush_events_clears_global_array() {
         reset_global_array().await;
-        push_event("Event 1".into()).await;
-        push_event("Event 2".into()).await;
+        push_event("Event 1".into()).await.unwrap();
+        push_event("Event 2".into()).await.unwrap();
         {
             let arr = GLOBAL_EVENT_ARRAY.read().await;
             assert_eq!(arr.query_all().await.len(), 2);
@@ -104,11 +359,57 @@ mod tests {
     async fn test_push_event_multiple_times_and_persist() {
         reset_global_array().await;
         for i in 0..5 {
-            push_event(format!("Msg {}", i)).await;
+            push_event(format!("Msg {}", i).into()).await.unwrap();
         }
         let arr = GLOBAL_EVENT_ARRAY.read().await;
         let events = arr.query_all().await;
-        let payloads: Vec<_> = events.iter().map(|e| e.payload.clone()).collect();
+        let payloads: Vec<_> = events
+            .iter()
+            .map(|e| e.payload.body.clone().unwrap())
+            .collect();
         assert_eq!(payloads, vec!["Msg 0", "Msg 1", "Msg 2", "Msg 3", "Msg 4"]);
     }
+
+    #[tokio::test]
+    #[serial]
+    async fn test_push_event_assigns_increasing_ids() {
+        reset_global_array().await;
+        let mut rx = events::EVENT_CHANNEL.subscribe();
+        let first = push_event("Seq 1".into()).await.unwrap();
+        let second = push_event("Seq 2".into()).await.unwrap();
+        assert_eq!(second.id, first.id + 1);
+        assert_eq!(last_event_id(), second.id);
+        assert_eq!(rx.recv().await.unwrap().id, first.id);
+        assert_eq!(rx.recv().await.unwrap().id, second.id);
+        assert_eq!(first_event_id().await, Some(first.id));
+        let after: Vec<_> = get_events_after(first.id)
+            .await
+            .into_iter()
+            .map(|e| e.payload.body.unwrap())
+            .collect();
+        assert_eq!(after, vec!["Seq 2"]);
+    }
+
+    #[tokio::test]
+    #[serial]
+    async fn test_snapshot_restores_buffer_and_sequence() {
+        reset_global_array().await;
+        let path = std::env::temp_dir()
+            .join(format!("rodan-snapshot-{}", rand::random::<u64>()))
+            .to_string_lossy()
+            .into_owned();
+        let first = push_event("Snap 1".into()).await.unwrap();
+        let second = push_event("Snap 2".into()).await.unwrap();
+        assert_eq!(save_snapshot(&path).await.unwrap(), 2);
+        reset_global_array().await;
+        assert_eq!(load_snapshot(&path).await.unwrap(), Some(2));
+        let restored = get_events(None).await;
+        assert_eq!(
+            restored.iter().map(|e| e.id).collect::<Vec<_>>(),
+            vec![first.id, second.id]
+        );
+        assert_eq!(restored[0].timestamp, first.timestamp);
+        assert_eq!(last_event_id(), second.id);
+        tokio::fs::remove_file(&path).await.unwrap();
+    }
 }
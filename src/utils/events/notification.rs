@@ -0,0 +1,201 @@
+// Copyright (c) 2025 Intraware
+// Licensed under the MIT License
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     https://opensource.org/licenses/MIT
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use crate::utils::events::{audience::Audience, topic::default_topic};
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Severity {
+    #[default]
+    Info,
+    Success,
+    Warning,
+    Error,
+    Critical,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Notification {
+    #[serde(rename = "type", default = "default_kind")]
+    pub kind: String,
+    #[serde(default)]
+    pub title: Option<String>,
+    #[serde(default)]
+    pub body: Option<String>,
+    #[serde(default)]
+    pub severity: Severity,
+    #[serde(default)]
+    pub metadata: Map<String, Value>,
+    #[serde(default)]
+    pub source: Option<String>,
+}
+
+fn default_kind() -> String {
+    "message".into()
+}
+
+impl Notification {
+    pub fn validate(&self) -> Result<(), String> {
+        if self.kind.trim().is_empty() {
+            return Err("event type cannot be empty".into());
+        }
+        Ok(())
+    }
+
+    // The body of a notification that could have been sent as a plain string.
+    pub fn plain_body(&self) -> Option<&str> {
+        let body = self.body.as_deref()?;
+        (*self == Notification::from(body)).then_some(body)
+    }
+}
+
+// Plain strings predate the envelope and are carried as the body of a "message".
+impl From<String> for Notification {
+    fn from(body: String) -> Self {
+        Notification {
+            kind: default_kind(),
+            title: None,
+            body: Some(body),
+            severity: Severity::default(),
+            metadata: Map::new(),
+            source: None,
+        }
+    }
+}
+
+impl From<&str> for Notification {
+    fn from(body: &str) -> Self {
+        Notification::from(body.to_string())
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(try_from = "RawEnvelope")]
+pub struct IncomingEnvelope {
+    pub notification: Notification,
+    pub audience: Audience,
+    pub topic: String,
+}
+
+// `deny_unknown_fields` does not work with `flatten`, so the keys no field
+// claims are collected and rejected by hand.
+#[derive(Deserialize)]
+struct RawEnvelope {
+    #[serde(flatten)]
+    notification: Notification,
+    #[serde(default)]
+    audience: Audience,
+    #[serde(default = "default_topic")]
+    topic: String,
+    #[serde(flatten)]
+    unknown: Map<String, Value>,
+}
+
+// A misspelt `audience` or `topic` would otherwise be dropped, sending the
+// event to everyone on the default topic.
+impl TryFrom<RawEnvelope> for IncomingEnvelope {
+    type Error = String;
+
+    fn try_from(raw: RawEnvelope) -> Result<Self, Self::Error> {
+        if let Some(key) = raw.unknown.keys().next() {
+            return Err(format!("unknown field '{}'", key));
+        }
+        Ok(IncomingEnvelope {
+            notification: raw.notification,
+            audience: raw.audience,
+            topic: raw.topic,
+        })
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+pub enum IncomingNotification {
+    Plain(String),
+    Envelope(IncomingEnvelope),
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_plain_string_becomes_message() {
+        let incoming: IncomingNotification = serde_json::from_value(json!("hello")).unwrap();
+        let IncomingNotification::Plain(body) = incoming else {
+            panic!("Expected a plain string");
+        };
+        let notification = Notification::from(body);
+        assert_eq!(notification.kind, "message");
+        assert_eq!(notification.body.as_deref(), Some("hello"));
+        assert_eq!(notification.severity, Severity::Info);
+        assert!(notification.metadata.is_empty());
+    }
+
+    #[test]
+    fn test_envelope_fields() {
+        let incoming: IncomingNotification = serde_json::from_value(json!({
+            "type": "announcement",
+            "title": "Round 2",
+            "body": "Starts in 5 minutes",
+            "severity": "warning",
+            "metadata": {"link": "/rounds/2"},
+            "source": "rodan-admin",
+            "audience": {"teams": [3]}
+        }))
+        .unwrap();
+        let IncomingNotification::Envelope(envelope) = incoming else {
+            panic!("Expected an envelope");
+        };
+        assert_eq!(envelope.audience.teams, vec![3]);
+        assert_eq!(envelope.topic, "default");
+        let notification = envelope.notification;
+        assert_eq!(notification.kind, "announcement");
+        assert_eq!(notification.title.as_deref(), Some("Round 2"));
+        assert_eq!(notification.severity, Severity::Warning);
+        assert_eq!(notification.metadata["link"], "/rounds/2");
+        assert_eq!(notification.source.as_deref(), Some("rodan-admin"));
+        assert!(notification.validate().is_ok());
+    }
+
+    #[test]
+    fn test_envelope_rejects_bad_input() {
+        let bad_severity = serde_json::from_value::<IncomingNotification>(json!({
+            "type": "announcement",
+            "severity": "loud"
+        }));
+        assert!(bad_severity.is_err());
+        let bad_metadata = serde_json::from_value::<IncomingNotification>(json!({
+            "type": "announcement",
+            "metadata": [1, 2]
+        }));
+        assert!(bad_metadata.is_err());
+        let bad_audience = serde_json::from_value::<IncomingNotification>(json!({
+            "type": "announcement",
+            "audience": "nobody"
+        }));
+        assert!(bad_audience.is_err());
+        for typo in ["audence", "topc"] {
+            let unknown = serde_json::from_value::<IncomingNotification>(json!({
+                "type": "announcement",
+                typo: {"teams": [3]}
+            }));
+            assert!(unknown.is_err(), "{} was accepted", typo);
+        }
+        let empty_kind: Notification = serde_json::from_value(json!({"type": " "})).unwrap();
+        assert!(empty_kind.validate().is_err());
+    }
+}
@@ -0,0 +1,202 @@
+// Copyright (c) 2025 Intraware
+// Licensed under the MIT License
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     https://opensource.org/licenses/MIT
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+// A snapshot is two lines: a JSON header followed by the JSON encoded
+// segments. The header records the format version and a SHA-256 over the
+// next id, the cursors and the segments line so that truncated or edited
+// files are rejected on load. The header also carries the read receipt
+// cursors, so restored events are not all unread again.
+
+use crate::utils::events::event::Event;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::{collections::HashMap, path::Path};
+use tokio::{fs, io::AsyncWriteExt};
+
+pub const SNAPSHOT_VERSION: u32 = 1;
+
+#[derive(Serialize, Deserialize)]
+struct SnapshotHeader {
+    version: u32,
+    created: DateTime<Utc>,
+    next_id: u64,
+    checksum: String,
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    cursors: HashMap<u64, u64>,
+}
+
+fn checksum(next_id: u64, cursors: &HashMap<u64, u64>, body: &str) -> String {
+    let mut cursors: Vec<(&u64, &u64)> = cursors.iter().collect();
+    cursors.sort_unstable();
+    let mut hasher = Sha256::new();
+    hasher.update(next_id.to_le_bytes());
+    for (user_id, id) in cursors {
+        hasher.update(user_id.to_le_bytes());
+        hasher.update(id.to_le_bytes());
+    }
+    hasher.update(body.as_bytes());
+    format!("{:x}", hasher.finalize())
+}
+
+pub struct Snapshot {
+    pub next_id: u64,
+    pub segments: Vec<Vec<Event>>,
+    pub cursors: HashMap<u64, u64>,
+}
+
+pub async fn write_snapshot(path: &str, snapshot: &Snapshot) -> Result<(), String> {
+    let body = serde_json::to_string(&snapshot.segments).map_err(|e| e.to_string())?;
+    let header = SnapshotHeader {
+        version: SNAPSHOT_VERSION,
+        created: Utc::now(),
+        next_id: snapshot.next_id,
+        checksum: checksum(snapshot.next_id, &snapshot.cursors, &body),
+        cursors: snapshot.cursors.clone(),
+    };
+    let header = serde_json::to_string(&header).map_err(|e| e.to_string())?;
+    if let Some(parent) = Path::new(path).parent() {
+        fs::create_dir_all(parent)
+            .await
+            .map_err(|e| e.to_string())?;
+    }
+    // Written next to the target and renamed so a crash never leaves half a snapshot.
+    let tmp_path = format!("{}.tmp", path);
+    let mut file = fs::File::create(&tmp_path)
+        .await
+        .map_err(|e| e.to_string())?;
+    file.write_all(format!("{}\n{}\n", header, body).as_bytes())
+        .await
+        .map_err(|e| e.to_string())?;
+    file.sync_all().await.map_err(|e| e.to_string())?;
+    fs::rename(&tmp_path, path)
+        .await
+        .map_err(|e| e.to_string())?;
+    Ok(())
+}
+
+pub async fn read_snapshot(path: &str) -> Result<Option<Snapshot>, String> {
+    let contents = match fs::read_to_string(path).await {
+        Ok(c) => c,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+        Err(e) => return Err(e.to_string()),
+    };
+    let (header, body) = contents
+        .split_once('\n')
+        .ok_or("snapshot is missing its header")?;
+    let header: SnapshotHeader =
+        serde_json::from_str(header).map_err(|e| format!("invalid snapshot header: {}", e))?;
+    if header.version != SNAPSHOT_VERSION {
+        return Err(format!(
+            "unsupported snapshot version {} (expected {})",
+            header.version, SNAPSHOT_VERSION
+        ));
+    }
+    let body = body.strip_suffix('\n').unwrap_or(body);
+    if checksum(header.next_id, &header.cursors, body) != header.checksum {
+        return Err("snapshot checksum mismatch".into());
+    }
+    let segments: Vec<Vec<Event>> =
+        serde_json::from_str(body).map_err(|e| format!("invalid snapshot body: {}", e))?;
+    Ok(Some(Snapshot {
+        next_id: header.next_id,
+        segments,
+        cursors: header.cursors,
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn temp_path() -> String {
+        std::env::temp_dir()
+            .join(format!("rodan-snapshot-test-{}", rand::random::<u64>()))
+            .to_string_lossy()
+            .into_owned()
+    }
+
+    fn snapshot() -> Snapshot {
+        Snapshot {
+            next_id: 4,
+            segments: vec![
+                vec![Event::for_test(1), Event::for_test(2)],
+                vec![Event::for_test(3)],
+            ],
+            cursors: HashMap::from([(7, 2)]),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_snapshot_roundtrip() {
+        let path = temp_path();
+        let original = snapshot();
+        write_snapshot(&path, &original).await.unwrap();
+        let loaded = read_snapshot(&path).await.unwrap().unwrap();
+        assert_eq!(loaded.next_id, 4);
+        assert_eq!(loaded.cursors, HashMap::from([(7, 2)]));
+        assert_eq!(loaded.segments.len(), 2);
+        assert_eq!(loaded.segments[0][1].id, 2);
+        assert_eq!(
+            loaded.segments[1][0].timestamp,
+            original.segments[1][0].timestamp
+        );
+        fs::remove_file(&path).await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_missing_snapshot() {
+        assert!(read_snapshot(&temp_path()).await.unwrap().is_none());
+    }
+
+    #[tokio::test]
+    async fn test_corrupt_snapshot_rejected() {
+        let path = temp_path();
+        write_snapshot(&path, &snapshot()).await.unwrap();
+        let contents = fs::read_to_string(&path).await.unwrap();
+        fs::write(&path, contents.replace("\"E2\"", "\"E9\""))
+            .await
+            .unwrap();
+        let err = read_snapshot(&path).await.err().unwrap();
+        assert!(err.contains("checksum"), "{}", err);
+        for (from, to) in [
+            ("\"next_id\":4", "\"next_id\":9"),
+            ("{\"7\":2}", "{\"7\":9}"),
+        ] {
+            fs::write(&path, contents.replacen(from, to, 1))
+                .await
+                .unwrap();
+            let err = read_snapshot(&path).await.err().unwrap();
+            assert!(err.contains("checksum"), "{}: {}", from, err);
+        }
+        fs::write(&path, "not a snapshot").await.unwrap();
+        assert!(read_snapshot(&path).await.is_err());
+        fs::remove_file(&path).await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_wrong_version_rejected() {
+        let path = temp_path();
+        write_snapshot(&path, &snapshot()).await.unwrap();
+        let contents = fs::read_to_string(&path).await.unwrap();
+        fs::write(
+            &path,
+            contents.replacen("\"version\":1", "\"version\":99", 1),
+        )
+        .await
+        .unwrap();
+        let err = read_snapshot(&path).await.err().unwrap();
+        assert!(err.contains("version"), "{}", err);
+        fs::remove_file(&path).await.unwrap();
+    }
+}
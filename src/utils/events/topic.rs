@@ -0,0 +1,72 @@
+// Copyright (c) 2025 Intraware
+// Licensed under the MIT License
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     https://opensource.org/licenses/MIT
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+pub const DEFAULT_TOPIC: &str = "default";
+const MAX_TOPIC_LENGTH: usize = 64;
+
+pub fn default_topic() -> String {
+    DEFAULT_TOPIC.into()
+}
+
+pub fn validate_topic(topic: &str) -> Result<(), String> {
+    if topic.is_empty() || topic.len() > MAX_TOPIC_LENGTH {
+        return Err(format!(
+            "topic must be between 1 and {} characters",
+            MAX_TOPIC_LENGTH
+        ));
+    }
+    if !topic
+        .chars()
+        .all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.'))
+    {
+        return Err(format!("topic '{}' contains invalid characters", topic));
+    }
+    Ok(())
+}
+
+pub fn parse_topics(raw: Option<&str>) -> Result<Vec<String>, String> {
+    let mut topics: Vec<String> = Vec::new();
+    for topic in raw.unwrap_or("").split(',').map(str::trim) {
+        if topic.is_empty() {
+            continue;
+        }
+        validate_topic(topic)?;
+        if !topics.iter().any(|t| t == topic) {
+            topics.push(topic.to_string());
+        }
+    }
+    Ok(topics)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_topics() {
+        assert!(parse_topics(None).unwrap().is_empty());
+        assert!(parse_topics(Some(" , ")).unwrap().is_empty());
+        assert_eq!(
+            parse_topics(Some("announcements, challenges,announcements")).unwrap(),
+            vec!["announcements", "challenges"]
+        );
+    }
+
+    #[test]
+    fn test_invalid_topics() {
+        assert!(parse_topics(Some("ok,not ok")).is_err());
+        assert!(validate_topic("").is_err());
+        assert!(validate_topic(&"a".repeat(65)).is_err());
+        assert!(validate_topic("scoreboard.ticks-v2_1").is_ok());
+    }
+}
@@ -0,0 +1,375 @@
+// Copyright (c) 2025 Intraware
+// Licensed under the MIT License
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     https://opensource.org/licenses/MIT
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+// Append-only log of ingested events. Each record is framed as
+//
+//     [len: u32 LE][checksum: u32 LE][payload: JSON encoded Event]
+//
+// where the checksum is the first four bytes of the payload's SHA-256. Records
+// are spread over segment files named after the first id they hold, so that
+// segments can be deleted once the in-memory buffer has evicted them. Segment
+// boundaries do not line up with the buffer's, so the last evicted id is kept
+// in a `logged` file and records up to it are skipped on recovery.
+
+use crate::{config::app::WalSync, utils::events::event::Event};
+use sha2::{Digest, Sha256};
+use std::{
+    collections::VecDeque,
+    io,
+    path::{Path, PathBuf},
+};
+use tokio::{
+    fs::{self, File, OpenOptions},
+    io::AsyncWriteExt,
+    sync::Mutex,
+};
+
+const HEADER_LEN: usize = 8;
+const SEGMENT_EXTENSION: &str = "wal";
+const LOGGED_FILE: &str = "logged";
+
+struct Segment {
+    path: PathBuf,
+    last_id: u64,
+}
+
+struct WalWriter {
+    file: File,
+    path: PathBuf,
+    len: u64,
+    records: usize,
+    last_id: u64,
+    logged: u64,
+    closed: VecDeque<Segment>,
+}
+
+pub struct Wal {
+    dir: PathBuf,
+    sync: WalSync,
+    segment_records: usize,
+    writer: Mutex<WalWriter>,
+}
+
+fn checksum(payload: &[u8]) -> u32 {
+    let digest = Sha256::digest(payload);
+    u32::from_le_bytes([digest[0], digest[1], digest[2], digest[3]])
+}
+
+fn encode(event: &Event) -> io::Result<Vec<u8>> {
+    let payload = serde_json::to_vec(event)?;
+    let mut record = Vec::with_capacity(HEADER_LEN + payload.len());
+    record.extend_from_slice(&(payload.len() as u32).to_le_bytes());
+    record.extend_from_slice(&checksum(&payload).to_le_bytes());
+    record.extend_from_slice(&payload);
+    Ok(record)
+}
+
+// Returns the events decoded from `bytes` and the length of the valid prefix.
+fn decode(bytes: &[u8]) -> (Vec<Event>, usize) {
+    let mut events = Vec::new();
+    let mut offset = 0;
+    while bytes.len() - offset >= HEADER_LEN {
+        let len = u32::from_le_bytes(bytes[offset..offset + 4].try_into().unwrap()) as usize;
+        let sum = u32::from_le_bytes(bytes[offset + 4..offset + 8].try_into().unwrap());
+        let start = offset + HEADER_LEN;
+        if bytes.len() - start < len {
+            break;
+        }
+        let payload = &bytes[start..start + len];
+        if checksum(payload) != sum {
+            break;
+        }
+        match serde_json::from_slice::<Event>(payload) {
+            Ok(event) => events.push(event),
+            Err(_) => break,
+        }
+        offset = start + len;
+    }
+    (events, offset)
+}
+
+fn segment_path(dir: &Path, first_id: u64) -> PathBuf {
+    dir.join(format!("{:020}.{}", first_id, SEGMENT_EXTENSION))
+}
+
+async fn open_segment(path: &Path) -> io::Result<File> {
+    OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(path)
+        .await
+}
+
+async fn list_segments(dir: &Path) -> io::Result<Vec<PathBuf>> {
+    let mut paths = Vec::new();
+    let mut entries = fs::read_dir(dir).await?;
+    while let Some(entry) = entries.next_entry().await? {
+        let path = entry.path();
+        if path.extension().and_then(|e| e.to_str()) == Some(SEGMENT_EXTENSION) {
+            paths.push(path);
+        }
+    }
+    paths.sort();
+    Ok(paths)
+}
+
+async fn read_logged(dir: &Path) -> io::Result<u64> {
+    match fs::read_to_string(dir.join(LOGGED_FILE)).await {
+        Ok(contents) => contents.trim().parse().map_err(|e| {
+            io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("{}: {}", dir.join(LOGGED_FILE).display(), e),
+            )
+        }),
+        Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(0),
+        Err(e) => Err(e),
+    }
+}
+
+async fn write_logged(dir: &Path, id: u64) -> io::Result<()> {
+    let path = dir.join(LOGGED_FILE);
+    let tmp_path = dir.join(format!("{}.tmp", LOGGED_FILE));
+    let mut file = File::create(&tmp_path).await?;
+    file.write_all(id.to_string().as_bytes()).await?;
+    file.sync_all().await?;
+    fs::rename(&tmp_path, &path).await
+}
+
+impl Wal {
+    // Opens the log in `dir`, returning it along with every event recovered
+    // from existing segments. Recovery stops at the first torn or corrupt
+    // record; that segment is truncated and any later segment is removed.
+    pub async fn open(
+        dir: impl Into<PathBuf>,
+        sync: WalSync,
+        segment_records: usize,
+    ) -> io::Result<(Wal, Vec<Event>)> {
+        let dir = dir.into();
+        fs::create_dir_all(&dir).await?;
+        let logged = read_logged(&dir).await?;
+        let mut last_id = 0;
+        let mut recovered: Vec<Event> = Vec::new();
+        let mut closed = VecDeque::new();
+        let mut corrupted = false;
+        for path in list_segments(&dir).await? {
+            if corrupted {
+                eprintln!("Removing WAL segment {} after corruption", path.display());
+                fs::remove_file(&path).await?;
+                continue;
+            }
+            let bytes = fs::read(&path).await?;
+            let (events, valid) = decode(&bytes);
+            if valid < bytes.len() {
+                eprintln!(
+                    "WAL segment {} is corrupt at byte {}, truncating",
+                    path.display(),
+                    valid
+                );
+                let file = OpenOptions::new().write(true).open(&path).await?;
+                file.set_len(valid as u64).await?;
+                file.sync_all().await?;
+                corrupted = true;
+            }
+            let events: Vec<Event> = events.into_iter().filter(|e| e.id > last_id).collect();
+            match events.last() {
+                Some(last) => {
+                    last_id = last.id;
+                    closed.push_back(Segment { path, last_id });
+                }
+                None => fs::remove_file(&path).await?,
+            }
+            recovered.extend(events.into_iter().filter(|e| e.id > logged));
+        }
+        let path = segment_path(&dir, last_id + 1);
+        let writer = WalWriter {
+            file: open_segment(&path).await?,
+            path,
+            len: 0,
+            records: 0,
+            last_id,
+            logged,
+            closed,
+        };
+        let wal = Wal {
+            dir,
+            sync,
+            segment_records: segment_records.max(1),
+            writer: Mutex::new(writer),
+        };
+        Ok((wal, recovered))
+    }
+
+    async fn roll(&self, writer: &mut WalWriter, first_id: u64) -> io::Result<()> {
+        writer.file.sync_all().await?;
+        let path = segment_path(&self.dir, first_id);
+        writer.file = open_segment(&path).await?;
+        let closed_path = std::mem::replace(&mut writer.path, path);
+        writer.closed.push_back(Segment {
+            path: closed_path,
+            last_id: writer.last_id,
+        });
+        writer.len = 0;
+        writer.records = 0;
+        Ok(())
+    }
+
+    pub async fn append(&self, event: &Event) -> io::Result<()> {
+        let record = encode(event)?;
+        let mut writer = self.writer.lock().await;
+        if writer.records >= self.segment_records {
+            self.roll(&mut writer, event.id).await?;
+        }
+        // tokio writes through a blocking thread, so the record only reaches
+        // the OS once the file is flushed.
+        let written = async {
+            writer.file.write_all(&record).await?;
+            writer.file.flush().await
+        }
+        .await;
+        if let Err(e) = written {
+            // Drop the partial record so later appends stay readable.
+            let _ = writer.file.set_len(writer.len).await;
+            return Err(e);
+        }
+        if self.sync == WalSync::Always {
+            writer.file.sync_data().await?;
+        }
+        writer.len += record.len() as u64;
+        writer.records += 1;
+        writer.last_id = event.id;
+        Ok(())
+    }
+
+    pub async fn sync(&self) -> io::Result<()> {
+        let writer = self.writer.lock().await;
+        writer.file.sync_data().await
+    }
+
+    // Records that everything before `oldest_retained_id` has been evicted,
+    // and drops closed segments that hold nothing else.
+    pub async fn compact(&self, oldest_retained_id: u64) -> io::Result<()> {
+        let logged = oldest_retained_id.saturating_sub(1);
+        let mut writer = self.writer.lock().await;
+        if logged > writer.logged {
+            write_logged(&self.dir, logged).await?;
+            writer.logged = logged;
+        }
+        while let Some(segment) = writer.closed.front() {
+            if segment.last_id > logged {
+                break;
+            }
+            fs::remove_file(&segment.path).await?;
+            writer.closed.pop_front();
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn temp_dir() -> PathBuf {
+        std::env::temp_dir().join(format!("rodan-wal-test-{}", rand::random::<u64>()))
+    }
+
+    fn ids(events: &[Event]) -> Vec<u64> {
+        events.iter().map(|e| e.id).collect()
+    }
+
+    #[tokio::test]
+    async fn test_append_and_recover() {
+        let dir = temp_dir();
+        let (wal, recovered) = Wal::open(&dir, WalSync::Always, 2).await.unwrap();
+        assert!(recovered.is_empty());
+        for id in 1..=5 {
+            wal.append(&Event::for_test(id)).await.unwrap();
+        }
+        drop(wal);
+        let (_, recovered) = Wal::open(&dir, WalSync::Always, 2).await.unwrap();
+        assert_eq!(ids(&recovered), vec![1, 2, 3, 4, 5]);
+        assert_eq!(recovered[0].payload.body.as_deref(), Some("E1"));
+        fs::remove_dir_all(&dir).await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_append_is_readable_without_sync() {
+        let dir = temp_dir();
+        let (wal, _) = Wal::open(&dir, WalSync::Os, 10).await.unwrap();
+        wal.append(&Event::for_test(1)).await.unwrap();
+        let path = list_segments(&dir).await.unwrap().pop().unwrap();
+        let (events, _) = decode(&fs::read(&path).await.unwrap());
+        assert_eq!(ids(&events), vec![1]);
+        drop(wal);
+        fs::remove_dir_all(&dir).await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_torn_tail_is_truncated() {
+        let dir = temp_dir();
+        let (wal, _) = Wal::open(&dir, WalSync::Os, 10).await.unwrap();
+        for id in 1..=3 {
+            wal.append(&Event::for_test(id)).await.unwrap();
+        }
+        wal.sync().await.unwrap();
+        drop(wal);
+        let path = list_segments(&dir).await.unwrap().pop().unwrap();
+        let mut bytes = fs::read(&path).await.unwrap();
+        let len = bytes.len();
+        bytes[len - 3] ^= 0xff;
+        fs::write(&path, &bytes).await.unwrap();
+        let (wal, recovered) = Wal::open(&dir, WalSync::Os, 10).await.unwrap();
+        assert_eq!(ids(&recovered), vec![1, 2]);
+        wal.append(&Event::for_test(3)).await.unwrap();
+        drop(wal);
+        let (_, recovered) = Wal::open(&dir, WalSync::Os, 10).await.unwrap();
+        assert_eq!(ids(&recovered), vec![1, 2, 3]);
+        fs::remove_dir_all(&dir).await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_compaction_removes_evicted_segments() {
+        let dir = temp_dir();
+        let (wal, _) = Wal::open(&dir, WalSync::Always, 2).await.unwrap();
+        for id in 1..=6 {
+            wal.append(&Event::for_test(id)).await.unwrap();
+        }
+        assert_eq!(list_segments(&dir).await.unwrap().len(), 3);
+        wal.compact(5).await.unwrap();
+        assert_eq!(list_segments(&dir).await.unwrap().len(), 1);
+        drop(wal);
+        let (_, recovered) = Wal::open(&dir, WalSync::Always, 2).await.unwrap();
+        assert_eq!(ids(&recovered), vec![5, 6]);
+        fs::remove_dir_all(&dir).await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_evicted_records_are_not_recovered() {
+        let dir = temp_dir();
+        let (wal, _) = Wal::open(&dir, WalSync::Always, 2).await.unwrap();
+        for id in 1..=5 {
+            wal.append(&Event::for_test(id)).await.unwrap();
+        }
+        // The segment holding 3 and 4 is kept for 4.
+        wal.compact(4).await.unwrap();
+        assert_eq!(list_segments(&dir).await.unwrap().len(), 2);
+        drop(wal);
+        let (wal, recovered) = Wal::open(&dir, WalSync::Always, 2).await.unwrap();
+        assert_eq!(ids(&recovered), vec![4, 5]);
+        wal.append(&Event::for_test(6)).await.unwrap();
+        drop(wal);
+        let (_, recovered) = Wal::open(&dir, WalSync::Always, 2).await.unwrap();
+        assert_eq!(ids(&recovered), vec![4, 5, 6]);
+        fs::remove_dir_all(&dir).await.unwrap();
+    }
+}
@@ -0,0 +1,176 @@
+use crate::utils::events::{buffer_responsive, wal_open};
+use crate::values::config::get_config;
+use chrono::Utc;
+use serde::Serialize;
+use std::{
+    collections::BTreeMap,
+    path::Path,
+    sync::atomic::{AtomicU8, Ordering},
+    time::Duration,
+};
+use tokio::fs;
+
+const BUFFER_LOCK_TIMEOUT: Duration = Duration::from_secs(1);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Lifecycle {
+    Starting,
+    Ready,
+    Draining,
+}
+
+// Ready by default so the router can be used without the binary's startup
+// sequence, which marks the service as starting until its state is restored.
+static LIFECYCLE: AtomicU8 = AtomicU8::new(Lifecycle::Ready as u8);
+
+pub fn lifecycle() -> Lifecycle {
+    match LIFECYCLE.load(Ordering::SeqCst) {
+        0 => Lifecycle::Starting,
+        1 => Lifecycle::Ready,
+        _ => Lifecycle::Draining,
+    }
+}
+
+pub fn set_lifecycle(state: Lifecycle) {
+    LIFECYCLE.store(state as u8, Ordering::SeqCst);
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum CheckStatus {
+    Ok,
+    Fail,
+    Skipped,
+}
+
+#[derive(Debug, Serialize)]
+pub struct Check {
+    pub status: CheckStatus,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub detail: Option<String>,
+}
+
+impl Check {
+    fn ok() -> Self {
+        Check {
+            status: CheckStatus::Ok,
+            detail: None,
+        }
+    }
+
+    fn fail(detail: impl Into<String>) -> Self {
+        Check {
+            status: CheckStatus::Fail,
+            detail: Some(detail.into()),
+        }
+    }
+
+    fn skipped(detail: impl Into<String>) -> Self {
+        Check {
+            status: CheckStatus::Skipped,
+            detail: Some(detail.into()),
+        }
+    }
+}
+
+pub type Checks = BTreeMap<&'static str, Check>;
+
+pub fn passed(checks: &Checks) -> bool {
+    checks.values().all(|c| c.status != CheckStatus::Fail)
+}
+
+async fn check_buffer() -> Check {
+    if buffer_responsive(BUFFER_LOCK_TIMEOUT).await {
+        Check::ok()
+    } else {
+        Check::fail(format!(
+            "buffer lock not acquired within {:?}",
+            BUFFER_LOCK_TIMEOUT
+        ))
+    }
+}
+
+// Opens the log the way the writer does, without writing to it.
+async fn check_event_log() -> Check {
+    let cfg = get_config();
+    let path = match &cfg.app.events_logfile {
+        Some(path) if cfg.app.event_logging => path.clone(),
+        _ => return Check::skipped("event logging is disabled"),
+    };
+    if let Some(parent) = Path::new(&path).parent()
+        && let Err(e) = fs::create_dir_all(parent).await
+    {
+        return Check::fail(format!("{}: {}", parent.display(), e));
+    }
+    match fs::OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(&path)
+        .await
+    {
+        Ok(_) => Check::ok(),
+        Err(e) => Check::fail(format!("{}: {}", path, e)),
+    }
+}
+
+fn check_ingest() -> Check {
+    let cfg = get_config();
+    let http = match cfg.app.events.as_ref().and_then(|e| e.http.as_ref()) {
+        Some(http) => http,
+        None => return Check::skipped("no ingest source is configured"),
+    };
+    let now = Utc::now();
+    if http.requires_key() && http.all_keys().iter().all(|k| k.is_expired(now)) {
+        return Check::fail("every ingest API key has expired");
+    }
+    Check::ok()
+}
+
+fn check_wal() -> Check {
+    if get_config().app.wal.is_none() {
+        return Check::skipped("no WAL is configured");
+    }
+    if wal_open() {
+        Check::ok()
+    } else {
+        Check::fail("WAL is not open")
+    }
+}
+
+fn check_lifecycle() -> Check {
+    match lifecycle() {
+        Lifecycle::Ready => Check::ok(),
+        Lifecycle::Starting => Check::fail("restoring state"),
+        Lifecycle::Draining => Check::fail("shutting down"),
+    }
+}
+
+// Liveness only fails for problems a restart would fix.
+pub async fn liveness() -> Checks {
+    Checks::from([("buffer", check_buffer().await)])
+}
+
+pub async fn readiness() -> Checks {
+    Checks::from([
+        ("lifecycle", check_lifecycle()),
+        ("buffer", check_buffer().await),
+        ("event_log", check_event_log().await),
+        ("ingest", check_ingest()),
+        ("wal", check_wal()),
+    ])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{config::Config, values::config::set_config};
+    use serial_test::serial;
+
+    #[test]
+    #[serial]
+    fn test_ingest_skipped_without_source() {
+        set_config(Config::default());
+        assert_eq!(check_ingest().status, CheckStatus::Skipped);
+    }
+}
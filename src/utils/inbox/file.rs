@@ -0,0 +1,327 @@
+// The file store keeps its state in memory and appends every change to a
+// JSON lines journal. On open, and whenever the journal has grown well past
+// what is still held, it is rewritten with only that, so it does not grow
+// with entries that were dropped.
+
+use super::{EntryState, InboxEntry, InboxStore, Recipient, memory::Inbox};
+use crate::utils::events::Event;
+use futures_util::future::BoxFuture;
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::HashMap,
+    io,
+    path::Path,
+    sync::{Mutex, MutexGuard},
+};
+use tokio::{
+    fs::{self, File, OpenOptions},
+    io::AsyncWriteExt,
+};
+
+#[derive(Serialize, Deserialize)]
+#[serde(tag = "op", rename_all = "lowercase")]
+pub(super) enum Op {
+    Add {
+        recipients: Vec<Recipient>,
+        event: Box<Event>,
+    },
+    Mark {
+        user_id: u64,
+        ids: Vec<u64>,
+        state: EntryState,
+    },
+    Cursor {
+        user_id: u64,
+        id: u64,
+    },
+}
+
+fn encode(op: &Op) -> io::Result<Vec<u8>> {
+    let mut line = serde_json::to_vec(op)?;
+    line.push(b'\n');
+    Ok(line)
+}
+
+// The journal is rewritten once it holds this many lines and twice as many
+// as a rewrite would leave.
+const COMPACT_AFTER: usize = 1000;
+
+struct Journal {
+    file: File,
+    lines: usize,
+    compacted: usize,
+}
+
+pub struct FileStore {
+    path: String,
+    compact_after: usize,
+    inbox: Mutex<Inbox>,
+    journal: tokio::sync::Mutex<Journal>,
+}
+
+// Written next to the journal and renamed so a crash keeps the old one.
+async fn rewrite(path: &str, ops: &[Op]) -> io::Result<File> {
+    let mut contents = Vec::new();
+    for op in ops {
+        contents.extend(encode(op)?);
+    }
+    let tmp_path = format!("{}.tmp", path);
+    let mut file = File::create(&tmp_path).await?;
+    file.write_all(&contents).await?;
+    file.sync_all().await?;
+    fs::rename(&tmp_path, path).await?;
+    OpenOptions::new().append(true).open(path).await
+}
+
+impl FileStore {
+    pub async fn open(path: &str, max_entries: usize) -> io::Result<FileStore> {
+        let mut inbox = Inbox::new(max_entries);
+        match fs::read_to_string(path).await {
+            Ok(contents) => {
+                let lines: Vec<&str> = contents.lines().collect();
+                for (i, line) in lines.iter().enumerate() {
+                    match serde_json::from_str::<Op>(line) {
+                        Ok(op) => inbox.apply(op),
+                        // A torn last line from a crash is skipped; anything
+                        // else means the journal was damaged.
+                        Err(_) if i + 1 == lines.len() => {
+                            eprintln!("Skipping torn last line of inbox journal {}", path)
+                        }
+                        Err(e) => {
+                            return Err(io::Error::new(
+                                io::ErrorKind::InvalidData,
+                                format!(
+                                    "inbox journal {} is corrupt at line {}: {}",
+                                    path,
+                                    i + 1,
+                                    e
+                                ),
+                            ));
+                        }
+                    }
+                }
+            }
+            Err(e) if e.kind() == io::ErrorKind::NotFound => {}
+            Err(e) => return Err(e),
+        }
+        if let Some(parent) = Path::new(path).parent() {
+            fs::create_dir_all(parent).await?;
+        }
+        let ops = inbox.journal();
+        let file = rewrite(path, &ops).await?;
+        Ok(FileStore {
+            path: path.to_string(),
+            compact_after: COMPACT_AFTER,
+            inbox: Mutex::new(inbox),
+            journal: tokio::sync::Mutex::new(Journal {
+                file,
+                lines: ops.len(),
+                compacted: ops.len(),
+            }),
+        })
+    }
+
+    fn inbox(&self) -> MutexGuard<'_, Inbox> {
+        self.inbox.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    async fn append(&self, journal: &mut Journal, op: &Op) -> io::Result<()> {
+        journal.file.write_all(&encode(op)?).await?;
+        journal.file.sync_data().await?;
+        journal.lines += 1;
+        Ok(())
+    }
+
+    // Called after the change is applied, so the rewrite includes it. The
+    // change is already journalled, so a failed rewrite is only reported.
+    async fn compact(&self, journal: &mut Journal) {
+        if journal.lines < self.compact_after || journal.lines < journal.compacted * 2 {
+            return;
+        }
+        let ops = self.inbox().journal();
+        match rewrite(&self.path, &ops).await {
+            Ok(file) => {
+                journal.file = file;
+                journal.lines = ops.len();
+                journal.compacted = ops.len();
+            }
+            Err(e) => eprintln!("Failed to compact inbox journal {}: {}", self.path, e),
+        }
+    }
+}
+
+impl InboxStore for FileStore {
+    // The journal lock is held across the change so lines are in the order
+    // the changes were made.
+    fn add(&self, recipients: Vec<Recipient>, event: Event) -> BoxFuture<'_, io::Result<()>> {
+        Box::pin(async move {
+            let mut journal = self.journal.lock().await;
+            let op = Op::Add {
+                recipients,
+                event: Box::new(event),
+            };
+            self.append(&mut journal, &op).await?;
+            self.inbox().apply(op);
+            self.compact(&mut journal).await;
+            Ok(())
+        })
+    }
+
+    fn entries(&self, user_id: u64, team_id: u64) -> BoxFuture<'_, io::Result<Vec<InboxEntry>>> {
+        let entries = self.inbox().entries(user_id, team_id);
+        Box::pin(async move { Ok(entries) })
+    }
+
+    // Only the ids that changed are journalled.
+    fn mark(
+        &self,
+        user_id: u64,
+        ids: Vec<u64>,
+        state: EntryState,
+    ) -> BoxFuture<'_, io::Result<usize>> {
+        Box::pin(async move {
+            let mut journal = self.journal.lock().await;
+            let ids = self.inbox().changes(user_id, &ids, state);
+            if ids.is_empty() {
+                return Ok(0);
+            }
+            let changed = ids.len();
+            let op = Op::Mark {
+                user_id,
+                ids,
+                state,
+            };
+            self.append(&mut journal, &op).await?;
+            self.inbox().apply(op);
+            self.compact(&mut journal).await;
+            Ok(changed)
+        })
+    }
+
+    fn last_id(&self) -> BoxFuture<'_, io::Result<u64>> {
+        let last_id = self.inbox().last_id();
+        Box::pin(async move { Ok(last_id) })
+    }
+
+    // A cursor that did not move is not journalled.
+    fn acknowledge(&self, user_id: u64, id: u64) -> BoxFuture<'_, io::Result<()>> {
+        Box::pin(async move {
+            let mut journal = self.journal.lock().await;
+            if !self.inbox().acknowledge(user_id, id) {
+                return Ok(());
+            }
+            self.append(&mut journal, &Op::Cursor { user_id, id })
+                .await?;
+            self.compact(&mut journal).await;
+            Ok(())
+        })
+    }
+
+    fn cursors(&self) -> BoxFuture<'_, io::Result<HashMap<u64, u64>>> {
+        let cursors = self.inbox().cursors();
+        Box::pin(async move { Ok(cursors) })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_file_store_survives_reopen() {
+        let dir = std::env::temp_dir().join(format!("rodan-inbox-{}", std::process::id()));
+        let path = dir.join("inbox.log").to_string_lossy().into_owned();
+        let _ = fs::remove_dir_all(&dir).await;
+        let store = FileStore::open(&path, 2).await.unwrap();
+        for id in 1..=3 {
+            store
+                .add(vec![Recipient::User(1)], Event::for_test(id))
+                .await
+                .unwrap();
+        }
+        store
+            .add(vec![Recipient::Team(4)], Event::for_test(4))
+            .await
+            .unwrap();
+        assert_eq!(
+            store.mark(1, vec![2, 4], EntryState::Read).await.unwrap(),
+            2
+        );
+        assert_eq!(
+            store.mark(1, vec![3], EntryState::Deleted).await.unwrap(),
+            1
+        );
+        store.acknowledge(1, 4).await.unwrap();
+        store.acknowledge(1, 2).await.unwrap();
+        drop(store);
+        // A torn line left by a crash is ignored.
+        let mut journal = OpenOptions::new().append(true).open(&path).await.unwrap();
+        journal.write_all(b"{\"op\":\"mark\",\"user").await.unwrap();
+        drop(journal);
+
+        let store = FileStore::open(&path, 2).await.unwrap();
+        let entries: Vec<(u64, bool)> = store
+            .entries(1, 4)
+            .await
+            .unwrap()
+            .into_iter()
+            .map(|e| (e.event.id, e.read))
+            .collect();
+        assert_eq!(entries, vec![(4, true), (2, true)]);
+        assert_eq!(store.cursors().await.unwrap(), HashMap::from([(1, 4)]));
+        let journal = fs::read_to_string(&path).await.unwrap();
+        assert_eq!(journal.lines().count(), 6);
+        fs::remove_dir_all(&dir).await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_corrupt_journal_is_rejected() {
+        let dir = std::env::temp_dir().join(format!("rodan-inbox-{}", rand::random::<u64>()));
+        let path = dir.join("inbox.log").to_string_lossy().into_owned();
+        let store = FileStore::open(&path, 10).await.unwrap();
+        for id in 1..=2 {
+            store
+                .add(vec![Recipient::User(1)], Event::for_test(id))
+                .await
+                .unwrap();
+        }
+        drop(store);
+        let journal = fs::read_to_string(&path).await.unwrap();
+        fs::write(&path, journal.replacen("{\"op\"", "{\"op", 1))
+            .await
+            .unwrap();
+        let err = FileStore::open(&path, 10).await.err().unwrap();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+        fs::remove_dir_all(&dir).await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_journal_is_compacted_as_it_grows() {
+        let dir = std::env::temp_dir().join(format!("rodan-inbox-{}", rand::random::<u64>()));
+        let path = dir.join("inbox.log").to_string_lossy().into_owned();
+        let mut store = FileStore::open(&path, 2).await.unwrap();
+        store.compact_after = 4;
+        for id in 1..=3 {
+            store
+                .add(vec![Recipient::User(1)], Event::for_test(id))
+                .await
+                .unwrap();
+        }
+        let lines = || async { fs::read_to_string(&path).await.unwrap().lines().count() };
+        assert_eq!(lines().await, 3);
+        // The fourth line triggers a rewrite down to the two entries kept.
+        store.mark(1, vec![3], EntryState::Read).await.unwrap();
+        assert_eq!(lines().await, 3);
+        drop(store);
+        let store = FileStore::open(&path, 2).await.unwrap();
+        let entries: Vec<(u64, bool)> = store
+            .entries(1, 0)
+            .await
+            .unwrap()
+            .into_iter()
+            .map(|e| (e.event.id, e.read))
+            .collect();
+        assert_eq!(entries, vec![(3, true), (2, false)]);
+        fs::remove_dir_all(&dir).await.unwrap();
+    }
+}
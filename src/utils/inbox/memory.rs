@@ -0,0 +1,307 @@
+use super::{EntryState, InboxEntry, InboxStore, Recipient, file::Op};
+use crate::utils::events::Event;
+use futures_util::future::BoxFuture;
+use std::{
+    collections::{BTreeMap, HashMap, VecDeque},
+    io,
+    sync::{Mutex, MutexGuard},
+};
+
+// The state shared by every store; the file store replays its journal into one.
+pub(super) struct Inbox {
+    max_entries: usize,
+    // Each event with the number of recipients still holding it.
+    events: HashMap<u64, (Event, usize)>,
+    recipients: HashMap<Recipient, VecDeque<u64>>,
+    states: HashMap<u64, HashMap<u64, EntryState>>,
+    last_id: u64,
+    cursors: HashMap<u64, u64>,
+}
+
+impl Inbox {
+    pub(super) fn new(max_entries: usize) -> Self {
+        Inbox {
+            max_entries,
+            events: HashMap::new(),
+            recipients: HashMap::new(),
+            states: HashMap::new(),
+            last_id: 0,
+            cursors: HashMap::new(),
+        }
+    }
+
+    // A recipient keeps its newest `max_entries` events; an event is dropped
+    // once no recipient holds it.
+    pub(super) fn add(&mut self, recipients: &[Recipient], event: Event) {
+        let id = event.id;
+        self.last_id = self.last_id.max(id);
+        let mut held = 0;
+        for recipient in recipients {
+            let ids = self.recipients.entry(*recipient).or_default();
+            if ids.contains(&id) {
+                continue;
+            }
+            ids.push_back(id);
+            held += 1;
+            if ids.len() > self.max_entries
+                && let Some(oldest) = ids.pop_front()
+            {
+                self.release(oldest);
+            }
+        }
+        if held > 0 {
+            self.events.entry(id).or_insert((event, 0)).1 += held;
+        }
+    }
+
+    fn release(&mut self, id: u64) {
+        let Some((_, count)) = self.events.get_mut(&id) else {
+            return;
+        };
+        *count -= 1;
+        if *count == 0 {
+            self.events.remove(&id);
+            for states in self.states.values_mut() {
+                states.remove(&id);
+            }
+        }
+    }
+
+    pub(super) fn last_id(&self) -> u64 {
+        self.last_id
+    }
+
+    // Returns whether the cursor moved; it only moves forward.
+    pub(super) fn acknowledge(&mut self, user_id: u64, id: u64) -> bool {
+        let cursor = self.cursors.entry(user_id).or_default();
+        if id <= *cursor {
+            return false;
+        }
+        *cursor = id;
+        true
+    }
+
+    pub(super) fn cursors(&self) -> HashMap<u64, u64> {
+        self.cursors.clone()
+    }
+
+    pub(super) fn entries(&self, user_id: u64, team_id: u64) -> Vec<InboxEntry> {
+        let states = self.states.get(&user_id);
+        let mut ids: Vec<u64> = [Recipient::User(user_id), Recipient::Team(team_id)]
+            .iter()
+            .filter_map(|r| self.recipients.get(r))
+            .flatten()
+            .copied()
+            .collect();
+        ids.sort_unstable_by(|a, b| b.cmp(a));
+        ids.dedup();
+        ids.into_iter()
+            .filter_map(|id| {
+                let state = states.and_then(|s| s.get(&id));
+                if state == Some(&EntryState::Deleted) {
+                    return None;
+                }
+                self.events.get(&id).map(|(event, _)| InboxEntry {
+                    event: event.clone(),
+                    read: state == Some(&EntryState::Read),
+                })
+            })
+            .collect()
+    }
+
+    // Returns the ids whose state `mark` would change; a deleted entry stays
+    // deleted.
+    pub(super) fn changes(&self, user_id: u64, ids: &[u64], state: EntryState) -> Vec<u64> {
+        let states = self.states.get(&user_id);
+        let mut changed = Vec::new();
+        for id in ids {
+            if !self.events.contains_key(id) || changed.contains(id) {
+                continue;
+            }
+            match states.and_then(|s| s.get(id)) {
+                Some(EntryState::Deleted) => {}
+                Some(current) if *current == state => {}
+                _ => changed.push(*id),
+            }
+        }
+        changed
+    }
+
+    // Returns the ids whose state changed.
+    pub(super) fn mark(&mut self, user_id: u64, ids: &[u64], state: EntryState) -> Vec<u64> {
+        let changed = self.changes(user_id, ids, state);
+        let states = self.states.entry(user_id).or_default();
+        for id in &changed {
+            states.insert(*id, state);
+        }
+        changed
+    }
+
+    // Events in id order with the recipients still holding them, then every
+    // user's marks and cursor: the shortest journal that rebuilds this state.
+    pub(super) fn journal(&self) -> Vec<Op> {
+        let mut held: BTreeMap<u64, Vec<Recipient>> = BTreeMap::new();
+        for (recipient, ids) in &self.recipients {
+            for id in ids {
+                held.entry(*id).or_default().push(*recipient);
+            }
+        }
+        let mut ops: Vec<Op> = held
+            .into_iter()
+            .filter_map(|(id, recipients)| {
+                self.events.get(&id).map(|(event, _)| Op::Add {
+                    recipients,
+                    event: Box::new(event.clone()),
+                })
+            })
+            .collect();
+        for (user_id, states) in &self.states {
+            for state in [EntryState::Read, EntryState::Deleted] {
+                let mut ids: Vec<u64> = states
+                    .iter()
+                    .filter(|(_, s)| **s == state)
+                    .map(|(id, _)| *id)
+                    .collect();
+                if !ids.is_empty() {
+                    ids.sort_unstable();
+                    ops.push(Op::Mark {
+                        user_id: *user_id,
+                        ids,
+                        state,
+                    });
+                }
+            }
+        }
+        let mut cursors: Vec<(u64, u64)> = self.cursors.iter().map(|(u, id)| (*u, *id)).collect();
+        cursors.sort_unstable();
+        ops.extend(
+            cursors
+                .into_iter()
+                .map(|(user_id, id)| Op::Cursor { user_id, id }),
+        );
+        ops
+    }
+
+    pub(super) fn apply(&mut self, op: Op) {
+        match op {
+            Op::Add { recipients, event } => self.add(&recipients, *event),
+            Op::Mark {
+                user_id,
+                ids,
+                state,
+            } => {
+                self.mark(user_id, &ids, state);
+            }
+            Op::Cursor { user_id, id } => {
+                self.acknowledge(user_id, id);
+            }
+        }
+    }
+}
+
+pub struct MemoryStore {
+    inbox: Mutex<Inbox>,
+}
+
+impl MemoryStore {
+    pub fn new(max_entries: usize) -> Self {
+        MemoryStore {
+            inbox: Mutex::new(Inbox::new(max_entries)),
+        }
+    }
+
+    fn inbox(&self) -> MutexGuard<'_, Inbox> {
+        self.inbox.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+impl InboxStore for MemoryStore {
+    fn add(&self, recipients: Vec<Recipient>, event: Event) -> BoxFuture<'_, io::Result<()>> {
+        self.inbox().add(&recipients, event);
+        Box::pin(async { Ok(()) })
+    }
+
+    fn entries(&self, user_id: u64, team_id: u64) -> BoxFuture<'_, io::Result<Vec<InboxEntry>>> {
+        let entries = self.inbox().entries(user_id, team_id);
+        Box::pin(async move { Ok(entries) })
+    }
+
+    fn mark(
+        &self,
+        user_id: u64,
+        ids: Vec<u64>,
+        state: EntryState,
+    ) -> BoxFuture<'_, io::Result<usize>> {
+        let changed = self.inbox().mark(user_id, &ids, state).len();
+        Box::pin(async move { Ok(changed) })
+    }
+
+    fn last_id(&self) -> BoxFuture<'_, io::Result<u64>> {
+        let last_id = self.inbox().last_id();
+        Box::pin(async move { Ok(last_id) })
+    }
+
+    fn acknowledge(&self, user_id: u64, id: u64) -> BoxFuture<'_, io::Result<()>> {
+        self.inbox().acknowledge(user_id, id);
+        Box::pin(async { Ok(()) })
+    }
+
+    fn cursors(&self) -> BoxFuture<'_, io::Result<HashMap<u64, u64>>> {
+        let cursors = self.inbox().cursors();
+        Box::pin(async move { Ok(cursors) })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ids(entries: Vec<InboxEntry>) -> Vec<(u64, bool)> {
+        entries.into_iter().map(|e| (e.event.id, e.read)).collect()
+    }
+
+    #[test]
+    fn test_team_entries_are_read_per_user() {
+        let mut inbox = Inbox::new(10);
+        inbox.add(&[Recipient::User(1)], Event::for_test(1));
+        inbox.add(&[Recipient::Team(5)], Event::for_test(2));
+        inbox.add(
+            &[Recipient::User(1), Recipient::Team(5)],
+            Event::for_test(3),
+        );
+        assert_eq!(
+            ids(inbox.entries(1, 5)),
+            vec![(3, false), (2, false), (1, false)]
+        );
+        assert_eq!(inbox.mark(1, &[2, 3, 99], EntryState::Read), vec![2, 3]);
+        assert_eq!(inbox.mark(1, &[2], EntryState::Read), Vec::<u64>::new());
+        assert_eq!(inbox.mark(2, &[3], EntryState::Deleted), vec![3]);
+        assert_eq!(
+            ids(inbox.entries(1, 5)),
+            vec![(3, true), (2, true), (1, false)]
+        );
+        assert_eq!(ids(inbox.entries(2, 5)), vec![(2, false)]);
+    }
+
+    #[test]
+    fn test_oldest_entries_are_dropped() {
+        let mut inbox = Inbox::new(2);
+        inbox.add(
+            &[Recipient::User(1), Recipient::Team(5)],
+            Event::for_test(1),
+        );
+        inbox.add(&[Recipient::User(1)], Event::for_test(2));
+        inbox.add(&[Recipient::User(1)], Event::for_test(3));
+        inbox.mark(1, &[1], EntryState::Read);
+        assert_eq!(ids(inbox.entries(1, 6)), vec![(3, false), (2, false)]);
+        // Still held by the team, with the mark kept.
+        assert_eq!(
+            ids(inbox.entries(1, 5)),
+            vec![(3, false), (2, false), (1, true)]
+        );
+        inbox.add(&[Recipient::Team(5)], Event::for_test(4));
+        inbox.add(&[Recipient::Team(5)], Event::for_test(5));
+        assert!(!inbox.events.contains_key(&1));
+        assert!(inbox.states[&1].is_empty());
+    }
+}
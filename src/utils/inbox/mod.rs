@@ -0,0 +1,185 @@
+mod file;
+mod memory;
+pub use file::FileStore;
+pub use memory::MemoryStore;
+
+use crate::{
+    config::app::InboxStoreKind,
+    utils::{
+        events::{Event, get_events_after},
+        receipts,
+    },
+    values::config::get_config,
+};
+use futures_util::future::BoxFuture;
+use once_cell::sync::Lazy;
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::HashMap,
+    io,
+    sync::{Arc, RwLock},
+};
+use tokio::sync::mpsc;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Recipient {
+    User(u64),
+    Team(u64),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum EntryState {
+    Read,
+    Deleted,
+}
+
+#[derive(Debug, Clone)]
+pub struct InboxEntry {
+    pub event: Event,
+    pub read: bool,
+}
+
+// Team entries are stored once and share the event; read and deleted are
+// tracked per user, so one member clearing an entry leaves it for the rest.
+pub trait InboxStore: Send + Sync {
+    fn add(&self, recipients: Vec<Recipient>, event: Event) -> BoxFuture<'_, io::Result<()>>;
+
+    // Entries of the user and their team, newest first, without deleted ones.
+    fn entries(&self, user_id: u64, team_id: u64) -> BoxFuture<'_, io::Result<Vec<InboxEntry>>>;
+
+    // Returns how many of `ids` changed state.
+    fn mark(
+        &self,
+        user_id: u64,
+        ids: Vec<u64>,
+        state: EntryState,
+    ) -> BoxFuture<'_, io::Result<usize>>;
+
+    // The highest event id added so far, or 0.
+    fn last_id(&self) -> BoxFuture<'_, io::Result<u64>>;
+
+    // Read receipt cursors are kept here too, so they outlive a restart.
+    fn acknowledge(&self, user_id: u64, id: u64) -> BoxFuture<'_, io::Result<()>>;
+
+    fn cursors(&self) -> BoxFuture<'_, io::Result<HashMap<u64, u64>>>;
+}
+
+struct Open {
+    store: Arc<dyn InboxStore>,
+    queue: mpsc::UnboundedSender<Event>,
+}
+
+static INBOX: Lazy<RwLock<Option<Open>>> = Lazy::new(|| RwLock::new(None));
+
+pub fn store() -> Option<Arc<dyn InboxStore>> {
+    let inbox = INBOX.read().unwrap_or_else(|e| e.into_inner());
+    inbox.as_ref().map(|open| open.store.clone())
+}
+
+// Events are added by one task in the order they were queued, so a slow store
+// never holds up publishing. Replacing the store lets the old task finish its
+// queue and exit.
+pub fn set_store(store: Option<Arc<dyn InboxStore>>) {
+    let open = store.map(|store| {
+        let (queue, mut rx) = mpsc::unbounded_channel::<Event>();
+        let worker = store.clone();
+        tokio::spawn(async move {
+            while let Some(event) = rx.recv().await {
+                add(worker.as_ref(), event).await;
+            }
+        });
+        Open { store, queue }
+    });
+    *INBOX.write().unwrap_or_else(|e| e.into_inner()) = open;
+}
+
+pub async fn init_inbox() -> io::Result<()> {
+    let Some(inbox) = get_config().app.inbox.clone() else {
+        return Ok(());
+    };
+    let store: Arc<dyn InboxStore> = match inbox.store {
+        InboxStoreKind::Memory => Arc::new(MemoryStore::new(inbox.max_entries)),
+        InboxStoreKind::File => {
+            let path = inbox.path.unwrap_or_default();
+            let store = FileStore::open(&path, inbox.max_entries).await?;
+            println!("Loaded inbox from {}", path);
+            Arc::new(store)
+        }
+    };
+    receipts::restore(store.cursors().await?);
+    // Events restored from the WAL or a snapshot may not have reached the
+    // store before the last shutdown.
+    let last_id = store.last_id().await?;
+    let missed = get_events_after(last_id).await;
+    for event in missed {
+        add(store.as_ref(), event).await;
+    }
+    set_store(Some(store));
+    Ok(())
+}
+
+// Only events naming users or teams are kept; broadcasts and role-only
+// alerts are not the kind of thing anyone needs to catch up on.
+pub fn recipients(event: &Event) -> Vec<Recipient> {
+    let users = event.audience.users.iter().map(|id| Recipient::User(*id));
+    let teams = event.audience.teams.iter().map(|id| Recipient::Team(*id));
+    users.chain(teams).collect()
+}
+
+async fn add(store: &dyn InboxStore, event: Event) {
+    let recipients = recipients(&event);
+    if recipients.is_empty() {
+        return;
+    }
+    let id = event.id;
+    if let Err(e) = store.add(recipients, event).await {
+        eprintln!("Failed to store event {} in the inbox: {}", id, e);
+    }
+}
+
+// Queues the event for the store; called in id order.
+pub fn deliver(event: &Event) {
+    let inbox = INBOX.read().unwrap_or_else(|e| e.into_inner());
+    if let Some(open) = inbox.as_ref()
+        && !recipients(event).is_empty()
+    {
+        let _ = open.queue.send(event.clone());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        config::Config,
+        utils::events::{Audience, EventDraft, push_event},
+        values::config::set_config,
+    };
+    use serial_test::serial;
+
+    #[tokio::test]
+    #[serial]
+    async fn test_buffered_events_are_delivered_on_open() {
+        set_store(None);
+        let event = push_event(EventDraft {
+            audience: Audience {
+                users: vec![931],
+                ..Default::default()
+            },
+            ..EventDraft::from("while closed")
+        })
+        .await
+        .unwrap();
+        let mut cfg = Config::default();
+        cfg.app.inbox = Some(toml::from_str("store = \"memory\"").unwrap());
+        set_config(cfg);
+        init_inbox().await.unwrap();
+        let entries = store().unwrap().entries(931, 0).await.unwrap();
+        set_store(None);
+        set_config(Config::default());
+        assert_eq!(entries.len(), 1);
+        assert_eq!(entries[0].event.id, event.id);
+    }
+}
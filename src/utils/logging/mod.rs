@@ -1,4 +1,4 @@
-use crate::values::config::get_config;
+use crate::{utils::metrics, values::config::get_config};
 use chrono::Utc;
 use tokio::fs;
 
@@ -30,8 +30,10 @@ pub async fn rotate_logs() {
         eprintln!("Failed to rotate log file: {}", e);
         return;
     }
+    metrics::record_rotation();
     if let Err(e) = fs::OpenOptions::new()
         .create(true)
+        .truncate(false)
         .write(true)
         .open(&path)
         .await
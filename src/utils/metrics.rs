@@ -0,0 +1,251 @@
+use crate::{
+    utils::{
+        connections,
+        events::{DEFAULT_TOPIC, buffer_stats},
+    },
+    values::config::get_config,
+};
+use once_cell::sync::Lazy;
+use std::{
+    collections::BTreeMap,
+    fmt::Write,
+    sync::{
+        Mutex,
+        atomic::{AtomicU64, Ordering},
+    },
+    time::Duration,
+};
+
+const LATENCY_BUCKETS: [f64; 11] = [
+    0.005, 0.01, 0.025, 0.05, 0.1, 0.25, 0.5, 1.0, 2.5, 5.0, 10.0,
+];
+
+static LAG_DROPPED: AtomicU64 = AtomicU64::new(0);
+static LOG_WRITE_FAILURES: AtomicU64 = AtomicU64::new(0);
+static ROTATIONS: AtomicU64 = AtomicU64::new(0);
+static INGESTED: Lazy<Mutex<BTreeMap<(String, String), u64>>> =
+    Lazy::new(|| Mutex::new(BTreeMap::new()));
+static LATENCY: Lazy<Mutex<BTreeMap<(String, String), Histogram>>> =
+    Lazy::new(|| Mutex::new(BTreeMap::new()));
+
+#[derive(Default)]
+struct Histogram {
+    buckets: [u64; LATENCY_BUCKETS.len()],
+    sum: f64,
+    count: u64,
+}
+
+impl Histogram {
+    fn observe(&mut self, secs: f64) {
+        for (bucket, le) in self.buckets.iter_mut().zip(LATENCY_BUCKETS) {
+            if secs <= le {
+                *bucket += 1;
+            }
+        }
+        self.sum += secs;
+        self.count += 1;
+    }
+}
+
+// Topics are chosen by producers, so only the default topic and those a key
+// is limited to get a label of their own; the rest share "other".
+fn topic_label(topic: &str) -> &str {
+    let cfg = get_config();
+    let known = topic == DEFAULT_TOPIC
+        || cfg
+            .app
+            .events
+            .as_ref()
+            .and_then(|e| e.http.as_ref())
+            .is_some_and(|http| {
+                http.keys
+                    .iter()
+                    .any(|k| k.topics.iter().any(|t| t == topic))
+            });
+    if known { topic } else { "other" }
+}
+
+pub fn record_ingest(key: &str, topic: &str) {
+    let topic = topic_label(topic);
+    let mut ingested = INGESTED.lock().unwrap_or_else(|e| e.into_inner());
+    *ingested.entry((key.into(), topic.into())).or_default() += 1;
+}
+
+pub fn record_lag(skipped: u64) {
+    LAG_DROPPED.fetch_add(skipped, Ordering::Relaxed);
+}
+
+pub fn record_log_write_failure() {
+    LOG_WRITE_FAILURES.fetch_add(1, Ordering::Relaxed);
+}
+
+pub fn record_rotation() {
+    ROTATIONS.fetch_add(1, Ordering::Relaxed);
+}
+
+pub fn observe_request(method: &str, route: &str, elapsed: Duration) {
+    let mut latency = LATENCY.lock().unwrap_or_else(|e| e.into_inner());
+    latency
+        .entry((method.into(), route.into()))
+        .or_default()
+        .observe(elapsed.as_secs_f64());
+}
+
+fn escape(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n")
+}
+
+fn header(out: &mut String, name: &str, kind: &str, help: &str) {
+    let _ = writeln!(out, "# HELP {} {}", name, help);
+    let _ = writeln!(out, "# TYPE {} {}", name, kind);
+}
+
+// Prometheus text exposition format, version 0.0.4.
+pub async fn render() -> String {
+    let mut out = String::new();
+    header(
+        &mut out,
+        "rodan_sse_connections",
+        "gauge",
+        "Open SSE connections.",
+    );
+    let _ = writeln!(out, "rodan_sse_connections {}", connections::count());
+
+    header(
+        &mut out,
+        "rodan_events_ingested_total",
+        "counter",
+        "Events accepted by the ingest endpoints, by API key and topic.",
+    );
+    for ((key, topic), count) in INGESTED.lock().unwrap_or_else(|e| e.into_inner()).iter() {
+        let _ = writeln!(
+            out,
+            "rodan_events_ingested_total{{key=\"{}\",topic=\"{}\"}} {}",
+            escape(key),
+            escape(topic),
+            count
+        );
+    }
+
+    header(
+        &mut out,
+        "rodan_broadcast_lagged_events_total",
+        "counter",
+        "Events skipped by subscribers that fell behind their broadcast channel.",
+    );
+    let _ = writeln!(
+        out,
+        "rodan_broadcast_lagged_events_total {}",
+        LAG_DROPPED.load(Ordering::Relaxed)
+    );
+
+    let stats = buffer_stats().await;
+    header(
+        &mut out,
+        "rodan_buffer_segments",
+        "gauge",
+        "Segments currently allocated in the event buffer.",
+    );
+    let _ = writeln!(out, "rodan_buffer_segments {}", stats.segments);
+    header(
+        &mut out,
+        "rodan_buffer_max_segments",
+        "gauge",
+        "Maximum number of segments in the event buffer.",
+    );
+    let _ = writeln!(out, "rodan_buffer_max_segments {}", stats.max_segments);
+    header(
+        &mut out,
+        "rodan_buffer_events",
+        "gauge",
+        "Events held in the event buffer.",
+    );
+    let _ = writeln!(out, "rodan_buffer_events {}", stats.events);
+    header(
+        &mut out,
+        "rodan_buffer_capacity",
+        "gauge",
+        "Events the event buffer can hold.",
+    );
+    let _ = writeln!(out, "rodan_buffer_capacity {}", stats.capacity);
+
+    header(
+        &mut out,
+        "rodan_log_write_failures_total",
+        "counter",
+        "Failed writes to the event log.",
+    );
+    let _ = writeln!(
+        out,
+        "rodan_log_write_failures_total {}",
+        LOG_WRITE_FAILURES.load(Ordering::Relaxed)
+    );
+    header(
+        &mut out,
+        "rodan_log_rotations_total",
+        "counter",
+        "Event log rotations.",
+    );
+    let _ = writeln!(
+        out,
+        "rodan_log_rotations_total {}",
+        ROTATIONS.load(Ordering::Relaxed)
+    );
+
+    header(
+        &mut out,
+        "rodan_http_request_duration_seconds",
+        "histogram",
+        "Time to produce a response, by method and route.",
+    );
+    for ((method, route), histogram) in LATENCY.lock().unwrap_or_else(|e| e.into_inner()).iter() {
+        let labels = format!("method=\"{}\",route=\"{}\"", escape(method), escape(route));
+        for (le, count) in LATENCY_BUCKETS.iter().zip(histogram.buckets) {
+            let _ = writeln!(
+                out,
+                "rodan_http_request_duration_seconds_bucket{{{},le=\"{}\"}} {}",
+                labels, le, count
+            );
+        }
+        let _ = writeln!(
+            out,
+            "rodan_http_request_duration_seconds_bucket{{{},le=\"+Inf\"}} {}",
+            labels, histogram.count
+        );
+        let _ = writeln!(
+            out,
+            "rodan_http_request_duration_seconds_sum{{{}}} {}",
+            labels, histogram.sum
+        );
+        let _ = writeln!(
+            out,
+            "rodan_http_request_duration_seconds_count{{{}}} {}",
+            labels, histogram.count
+        );
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_histogram_buckets_are_cumulative() {
+        let mut histogram = Histogram::default();
+        histogram.observe(0.02);
+        histogram.observe(3.0);
+        assert_eq!(histogram.buckets[0], 0);
+        assert_eq!(histogram.buckets[2], 1);
+        assert_eq!(histogram.buckets[9], 2);
+        assert_eq!(histogram.count, 2);
+    }
+
+    #[test]
+    fn test_label_escaping() {
+        assert_eq!(escape("a\"b\\c\nd"), "a\\\"b\\\\c\\nd");
+    }
+}
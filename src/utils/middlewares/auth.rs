@@ -1,29 +1,277 @@
-use crate::responses::unauthorized;
-use crate::utils::auth;
+use crate::config::app::{AuthPolicy, RouteGroup};
+use crate::responses::{forbidden, unauthorized};
+use crate::utils::{
+    auth::{self, Claims},
+    revocation, tickets,
+};
+use crate::values::config::get_config;
 use actix_web::{
     HttpMessage,
     body::BoxBody,
     dev::{ServiceRequest, ServiceResponse},
     middleware::Next,
+    web,
 };
+use chrono::Utc;
+
+pub const TOKEN_QUERY_PARAM: &str = "access_token";
+
+const STREAM_ROUTE: &str = "/api/notify";
+// Routes an `EventSource` connects to; browsers cannot set headers there.
+const BROWSER_ROUTES: [&str; 2] = [STREAM_ROUTE, "/api/events/history"];
+
+#[derive(serde::Deserialize)]
+struct CredentialQuery {
+    access_token: Option<String>,
+    ticket: Option<String>,
+}
+
+fn credential_query(req: &ServiceRequest) -> Option<CredentialQuery> {
+    web::Query::<CredentialQuery>::from_query(req.query_string())
+        .ok()
+        .map(|q| q.into_inner())
+}
+
+fn stream_ticket(req: &ServiceRequest) -> Option<String> {
+    if req.path() != STREAM_ROUTE {
+        return None;
+    }
+    credential_query(req)?.ticket
+}
+
+fn browser_token(req: &ServiceRequest) -> Option<String> {
+    if !BROWSER_ROUTES.contains(&req.path()) {
+        return None;
+    }
+    let security = &get_config().server.security;
+    if let Some(name) = &security.token_cookie
+        && let Some(cookie) = req.cookie(name)
+    {
+        return Some(cookie.value().to_string());
+    }
+    if security.query_token {
+        return credential_query(req)?.access_token;
+    }
+    None
+}
+
+fn authenticate(req: &ServiceRequest) -> Result<Claims, &'static str> {
+    let claims = read_claims(req)?;
+    if revocation::is_revoked(&claims) {
+        return Err("Session has been revoked");
+    }
+    Ok(claims)
+}
+
+fn read_claims(req: &ServiceRequest) -> Result<Claims, &'static str> {
+    let token = if req.headers().contains_key("Authorization") {
+        match auth::bearer_token(req.headers()) {
+            Some(token) => token.to_string(),
+            None => return Err("Bearer token is required"),
+        }
+    } else if let Some(ticket) = stream_ticket(req) {
+        return tickets::redeem(&ticket).ok_or("Invalid or expired ticket");
+    } else {
+        match browser_token(req) {
+            Some(token) => token,
+            None => return Err("Authorization header is required"),
+        }
+    };
+    auth::decode_jwt(&token)
+        .map(|data| data.claims)
+        .map_err(|_| "Invalid token")
+}
 
 pub async fn auth_middleware(
     req: ServiceRequest,
     next: Next<BoxBody>,
 ) -> Result<ServiceResponse<BoxBody>, actix_web::Error> {
-    let auth_header = match req.headers().get("Authorization") {
-        Some(h) => h,
-        None => return Ok(unauthorized(req, "Authorization header is required")),
-    };
-    let auth_str = auth_header.to_str().unwrap_or("");
-    if !auth_str.starts_with("Bearer ") {
-        return Ok(unauthorized(req, "Bearer token is required"));
-    }
-    let token = &auth_str[7..];
-    let token_data = match auth::decode_jwt(token) {
-        Ok(data) => data,
-        Err(_) => return Ok(unauthorized(req, "Invalid token")),
-    };
-    req.extensions_mut().insert(token_data);
-    next.call(req).await
+    match authenticate(&req) {
+        Ok(claims) => {
+            req.extensions_mut().insert(claims);
+            next.call(req).await
+        }
+        Err(msg) => Ok(unauthorized(req, msg)),
+    }
+}
+
+// The ingest handler checks its own keys because signatures cover the body;
+// other groups accept the hashed key in `x-api-key`.
+fn check_api_key(req: &ServiceRequest, group: RouteGroup) -> Result<(), String> {
+    if group == RouteGroup::Ingest {
+        return Ok(());
+    }
+    let cfg = get_config();
+    let http_cfg = cfg
+        .app
+        .events
+        .as_ref()
+        .and_then(|e| e.http.as_ref())
+        .ok_or("No API keys are configured")?;
+    let key = auth::match_api_key(http_cfg, req.headers())?;
+    if key.is_expired(Utc::now()) {
+        return Err(format!("API key '{}' has expired", key.name));
+    }
+    Ok(())
+}
+
+// Policies are looked up per request so a config reload applies them at once.
+pub async fn policy_middleware(
+    group: RouteGroup,
+    req: ServiceRequest,
+    next: Next<BoxBody>,
+) -> Result<ServiceResponse<BoxBody>, actix_web::Error> {
+    let policy = get_config().app.auth_policy(group);
+    match policy {
+        AuthPolicy::Public => next.call(req).await,
+        AuthPolicy::Jwt => auth_middleware(req, next).await,
+        AuthPolicy::ApiKey => match check_api_key(&req, group) {
+            Ok(()) => next.call(req).await,
+            Err(msg) => Ok(unauthorized(req, &msg)),
+        },
+        AuthPolicy::Role(role) => match authenticate(&req) {
+            Ok(claims) if claims.has_role(&role) => {
+                req.extensions_mut().insert(claims);
+                next.call(req).await
+            }
+            Ok(_) => Ok(forbidden(req, &format!("The '{}' role is required", role))),
+            Err(msg) => Ok(unauthorized(req, msg)),
+        },
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::router::{create_app, test_support::*};
+    use crate::values::config::set_config;
+    use actix_web::{App, http::StatusCode, test};
+
+    #[actix_web::test]
+    #[serial_test::serial]
+    async fn test_browser_token_sources() {
+        use actix_web::cookie::Cookie;
+        let mut cfg = auth_config();
+        cfg.server.security.token_cookie = Some("rodan_token".into());
+        cfg.server.security.query_token = true;
+        set_config(cfg);
+        let app = test::init_service(App::new().configure(create_app)).await;
+        let token = token_for(1, 1);
+
+        let req = test::TestRequest::get()
+            .uri(&format!("/api/notify?access_token={}", token))
+            .to_request();
+        assert_eq!(test::call_service(&app, req).await.status(), StatusCode::OK);
+        let req = test::TestRequest::get()
+            .uri("/api/events/history")
+            .cookie(Cookie::new("rodan_token", token.clone()))
+            .to_request();
+        assert_eq!(test::call_service(&app, req).await.status(), StatusCode::OK);
+        let req = test::TestRequest::get()
+            .uri(&format!("/api/events?access_token={}", token))
+            .to_request();
+        assert_eq!(
+            test::call_service(&app, req).await.status(),
+            StatusCode::UNAUTHORIZED
+        );
+        let req = test::TestRequest::get()
+            .uri("/api/notify?access_token=not-a-token")
+            .to_request();
+        assert_eq!(
+            test::call_service(&app, req).await.status(),
+            StatusCode::UNAUTHORIZED
+        );
+
+        let mut cfg = auth_config();
+        cfg.server.security.token_cookie = Some("rodan_token".into());
+        set_config(cfg);
+        let req = test::TestRequest::get()
+            .uri(&format!("/api/notify?access_token={}", token))
+            .to_request();
+        assert_eq!(
+            test::call_service(&app, req).await.status(),
+            StatusCode::UNAUTHORIZED
+        );
+    }
+
+    #[actix_web::test]
+    #[serial_test::serial]
+    async fn test_route_group_policies() {
+        use crate::config::app::AuthPolicies;
+        use sha2::{Digest, Sha256};
+        let hashed = format!("{:x}", Sha256::digest(b"core-key-0123456789"));
+        let mut cfg = auth_config();
+        let http = cfg.app.events.as_mut().unwrap().http.as_mut().unwrap();
+        http.api_key = Some("core-key-0123456789".into());
+        http.hashed_api_key = Some(hashed.clone());
+        set_config(cfg);
+        let app = test::init_service(App::new().configure(create_app)).await;
+        let get = |uri: &str| test::TestRequest::get().uri(uri).to_request();
+
+        // auth-required alone no longer puts ping or ingest behind a JWT.
+        let resp = test::call_service(&app, get("/api/ping")).await;
+        assert_eq!(resp.status(), StatusCode::OK);
+        let req = test::TestRequest::post()
+            .uri("/api/ingest/event")
+            .insert_header(("x-api-key", hashed.clone()))
+            .set_json(serde_json::json!({ "events": ["from core"] }))
+            .to_request();
+        assert_eq!(test::call_service(&app, req).await.status(), StatusCode::OK);
+        let resp = test::call_service(&app, get("/api/events")).await;
+        assert_eq!(resp.status(), StatusCode::UNAUTHORIZED);
+
+        let mut cfg = auth_config();
+        cfg.app.events = get_config().app.events.clone();
+        cfg.app.auth = toml::from_str::<AuthPolicies>(
+            r#"
+            ping = "jwt"
+            events = "api-key"
+            notify = { role = "admin" }
+            "#,
+        )
+        .unwrap();
+        cfg.app.validate().unwrap();
+        set_config(cfg);
+        let resp = test::call_service(&app, get("/api/ping")).await;
+        assert_eq!(resp.status(), StatusCode::UNAUTHORIZED);
+        let resp = test::call_service(&app, get("/api/events/history")).await;
+        assert_eq!(resp.status(), StatusCode::UNAUTHORIZED);
+        let req = test::TestRequest::get()
+            .uri("/api/events")
+            .insert_header(("x-api-key", hashed))
+            .to_request();
+        assert_eq!(test::call_service(&app, req).await.status(), StatusCode::OK);
+
+        let admin = admin_token();
+        let notify = |token: &str| {
+            test::TestRequest::get()
+                .uri("/api/notify")
+                .insert_header(("Authorization", format!("Bearer {}", token)))
+                .to_request()
+        };
+        let resp = test::call_service(&app, notify(&token_for(5, 3))).await;
+        assert_eq!(resp.status(), StatusCode::FORBIDDEN);
+        let resp = test::call_service(&app, notify(&admin)).await;
+        assert_eq!(resp.status(), StatusCode::OK);
+
+        let mut cfg = auth_config();
+        cfg.app.auth.events = Some(AuthPolicy::ApiKey);
+        assert!(cfg.app.validate().is_err());
+        cfg.app.auth.events = Some(AuthPolicy::Role(" ".into()));
+        assert!(cfg.app.validate().is_err());
+        let mut cfg = auth_config();
+        cfg.app.auth.ingest = Some(AuthPolicy::ApiKey);
+        assert!(cfg.app.validate().is_err());
+
+        // A public ingest policy skips the configured keys.
+        let mut cfg = auth_config();
+        cfg.app.events = get_config().app.events.clone();
+        cfg.app.auth.ingest = Some(AuthPolicy::Public);
+        set_config(cfg);
+        let req = test::TestRequest::post()
+            .uri("/api/ingest/event")
+            .set_json(serde_json::json!({ "events": ["open"] }))
+            .to_request();
+        assert_eq!(test::call_service(&app, req).await.status(), StatusCode::OK);
+    }
 }
@@ -1,15 +1,54 @@
 use crate::values::config::get_config;
 use actix_cors::Cors;
 
+// Origins are checked against the config on every request so that a reload
+// takes effect without rebuilding the app. Credentials are allowed so that a
+// token cookie reaches the event stream.
 pub fn cors_middleware() -> Cors {
-    let config = &get_config().server;
-    let mut cors = Cors::default().allow_any_header().allow_any_method();
-    if config.cors_url.len() == 1 && config.cors_url[0] == "*" && !config.production {
-        cors = cors.allow_any_origin().send_wildcard();
-    } else {
-        for origin in &config.cors_url {
-            cors = cors.allowed_origin(origin);
-        }
+    Cors::default()
+        .allow_any_header()
+        .allow_any_method()
+        .supports_credentials()
+        .allowed_origin_fn(|origin, _| {
+            let config = &get_config().server;
+            if config.cors_url.len() == 1 && config.cors_url[0] == "*" && !config.production {
+                return true;
+            }
+            origin
+                .to_str()
+                .is_ok_and(|origin| config.cors_url.iter().any(|url| url == origin))
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::router::{create_app, test_support::*};
+    use crate::values::config::set_config;
+    use actix_web::{App, http::StatusCode, test};
+
+    #[actix_web::test]
+    #[serial_test::serial]
+    async fn test_cors_follows_reloaded_config() {
+        set_config(test_config());
+        let app = test::init_service(App::new().configure(create_app)).await;
+        let preflight = |origin: &str| {
+            test::TestRequest::default()
+                .method(actix_web::http::Method::OPTIONS)
+                .uri("/api/ping")
+                .insert_header(("Origin", origin))
+                .insert_header(("Access-Control-Request-Method", "GET"))
+                .to_request()
+        };
+        let resp = test::call_service(&app, preflight("http://localhost:3000")).await;
+        assert_eq!(resp.status(), StatusCode::OK);
+        let resp = test::call_service(&app, preflight("https://ctf.example.com")).await;
+        assert_ne!(resp.status(), StatusCode::OK);
+        let mut cfg = test_config();
+        cfg.server.cors_url = vec!["https://ctf.example.com".into()];
+        set_config(cfg);
+        let resp = test::call_service(&app, preflight("https://ctf.example.com")).await;
+        assert_eq!(resp.status(), StatusCode::OK);
+        let resp = test::call_service(&app, preflight("http://localhost:3000")).await;
+        assert_ne!(resp.status(), StatusCode::OK);
     }
-    cors
 }
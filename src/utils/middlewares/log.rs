@@ -1,10 +1,51 @@
+use crate::utils::{middlewares::auth::TOKEN_QUERY_PARAM, tickets::TICKET_QUERY_PARAM};
 use actix_web::{
     Error,
     body::BoxBody,
     dev::{ServiceRequest, ServiceResponse},
-    middleware::Next,
+    middleware::{Logger, Next},
+    web,
 };
 
+// Names are decoded the way the auth middleware decodes them, so an encoded
+// name such as `access%5Ftoken` is redacted too.
+fn is_credential(name: &str) -> bool {
+    let decoded = web::Query::<Vec<(String, String)>>::from_query(name)
+        .ok()
+        .and_then(|pairs| pairs.into_inner().into_iter().next())
+        .map(|(name, _)| name);
+    matches!(
+        decoded.as_deref(),
+        Some(TOKEN_QUERY_PARAM | TICKET_QUERY_PARAM)
+    )
+}
+
+fn redact_query(query: &str) -> String {
+    query
+        .split('&')
+        .map(|pair| match pair.split_once('=') {
+            Some((name, _)) if is_credential(name) => format!("{}=[redacted]", name),
+            _ => pair.to_string(),
+        })
+        .collect::<Vec<_>>()
+        .join("&")
+}
+
+// Same as `Logger::default()`, except credentials passed in the query string
+// are not written to the log.
+pub fn access_logger() -> Logger {
+    Logger::new(r#"%a "%{request}xi" %s %b "%{Referer}i" "%{User-Agent}i" %T"#)
+        .custom_request_replace("request", |req| {
+            let query = req.query_string();
+            let target = if query.is_empty() {
+                req.path().to_string()
+            } else {
+                format!("{}?{}", req.path(), redact_query(query))
+            };
+            format!("{} {} {:?}", req.method(), target, req.version())
+        })
+}
+
 pub async fn log_middleware(
     req: ServiceRequest,
     next: Next<BoxBody>,
@@ -34,3 +75,22 @@ pub async fn log_middleware(
     println!("{}", log_entry);
     Ok(res)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_redact_query() {
+        assert_eq!(
+            redact_query("topics=a,b&access_token=secret.jwt.value"),
+            "topics=a,b&access_token=[redacted]"
+        );
+        assert_eq!(redact_query("ticket=abcdef"), "ticket=[redacted]");
+        assert_eq!(redact_query("format=legacy"), "format=legacy");
+        assert_eq!(
+            redact_query("access%5Ftoken=secret&tick%65t=abc"),
+            "access%5Ftoken=[redacted]&tick%65t=[redacted]"
+        );
+    }
+}
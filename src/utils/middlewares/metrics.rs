@@ -0,0 +1,24 @@
+use crate::utils::metrics;
+use actix_web::{
+    Error,
+    body::BoxBody,
+    dev::{ServiceRequest, ServiceResponse},
+    middleware::Next,
+};
+use std::time::Instant;
+
+// Labels by route pattern rather than path so ids in the URL do not create
+// new series.
+pub async fn metrics_middleware(
+    req: ServiceRequest,
+    next: Next<BoxBody>,
+) -> Result<ServiceResponse<BoxBody>, Error> {
+    let started = Instant::now();
+    let res = next.call(req).await?;
+    let route = res
+        .request()
+        .match_pattern()
+        .unwrap_or_else(|| "unmatched".into());
+    metrics::observe_request(res.request().method().as_str(), &route, started.elapsed());
+    Ok(res)
+}
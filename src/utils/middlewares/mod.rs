@@ -1,3 +1,4 @@
 pub mod auth;
 pub mod cors;
 pub mod log;
+pub mod metrics;
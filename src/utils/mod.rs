@@ -1,8 +1,18 @@
 #[path = "auth/auth.rs"]
-mod auth;
+pub mod auth;
 mod logging;
 
+pub mod connections;
 pub mod events;
+pub mod health;
+pub mod inbox;
+pub mod metrics;
 pub mod middlewares;
+pub mod presence;
+pub mod receipts;
+pub mod revocation;
+pub mod signing;
+pub mod sse;
+pub mod tickets;
 pub mod values;
 pub use logging::rotate_logs;
@@ -0,0 +1,135 @@
+use crate::{
+    config::app::PresenceAudience,
+    utils::{auth::Claims, events::Audience},
+    values::config::get_config,
+};
+use once_cell::sync::Lazy;
+use std::{collections::HashMap, sync::Mutex};
+use tokio::sync::broadcast;
+
+// Reserved for presence frames, which streams subscribed to it receive.
+pub const PRESENCE_TOPIC: &str = "presence";
+
+// Presence is current state, so updates go straight to open streams and are
+// never stored, replayed or given an event id.
+#[derive(Debug, Clone)]
+pub struct PresenceUpdate {
+    pub team_id: u64,
+    pub online: usize,
+    pub audience: Audience,
+}
+
+static UPDATES: Lazy<broadcast::Sender<PresenceUpdate>> = Lazy::new(|| broadcast::channel(64).0);
+
+pub fn subscribe() -> broadcast::Receiver<PresenceUpdate> {
+    UPDATES.subscribe()
+}
+
+#[derive(Default)]
+struct TeamPresence {
+    // Open streams per user; a user is online while they have at least one.
+    members: HashMap<u64, usize>,
+    published: usize,
+    pending: bool,
+}
+
+static TEAMS: Lazy<Mutex<HashMap<u64, TeamPresence>>> = Lazy::new(|| Mutex::new(HashMap::new()));
+
+fn teams() -> std::sync::MutexGuard<'static, HashMap<u64, TeamPresence>> {
+    TEAMS.lock().unwrap_or_else(|e| e.into_inner())
+}
+
+pub fn joined(claims: &Claims) {
+    let mut teams = teams();
+    let team = teams.entry(claims.team_id).or_default();
+    *team.members.entry(claims.user_id).or_default() += 1;
+    schedule(claims.team_id, team);
+}
+
+pub fn left(claims: &Claims) {
+    let mut teams = teams();
+    let Some(team) = teams.get_mut(&claims.team_id) else {
+        return;
+    };
+    if let Some(count) = team.members.get_mut(&claims.user_id) {
+        *count -= 1;
+        if *count == 0 {
+            team.members.remove(&claims.user_id);
+        }
+    }
+    schedule(claims.team_id, team);
+    if team.members.is_empty() && team.published == 0 && !team.pending {
+        teams.remove(&claims.team_id);
+    }
+}
+
+pub fn online(team_id: u64) -> usize {
+    teams().get(&team_id).map_or(0, |t| t.members.len())
+}
+
+pub fn all_online() -> Vec<(u64, usize)> {
+    let mut online: Vec<_> = teams()
+        .iter()
+        .filter(|(_, t)| !t.members.is_empty())
+        .map(|(id, t)| (*id, t.members.len()))
+        .collect();
+    online.sort();
+    online
+}
+
+// Changes are collected for the debounce window and published once, and only
+// if the count differs from the last one sent, so a quick reconnect is silent.
+fn schedule(team_id: u64, team: &mut TeamPresence) {
+    let Some(presence) = get_config().app.presence.clone() else {
+        return;
+    };
+    if team.pending {
+        return;
+    }
+    let Ok(runtime) = tokio::runtime::Handle::try_current() else {
+        return;
+    };
+    team.pending = true;
+    runtime.spawn(async move {
+        tokio::time::sleep(presence.debounce).await;
+        publish(team_id);
+    });
+}
+
+fn publish(team_id: u64) {
+    let online = {
+        let mut teams = teams();
+        let Some(team) = teams.get_mut(&team_id) else {
+            return;
+        };
+        team.pending = false;
+        let online = team.members.len();
+        let changed = online != team.published;
+        team.published = online;
+        if online == 0 {
+            teams.remove(&team_id);
+        }
+        if !changed {
+            return;
+        }
+        online
+    };
+    let Some(presence) = get_config().app.presence.clone() else {
+        return;
+    };
+    let audience = match presence.audience {
+        PresenceAudience::Team => Audience {
+            teams: vec![team_id],
+            ..Default::default()
+        },
+        PresenceAudience::Admins => Audience {
+            roles: vec![presence.admin_role],
+            ..Default::default()
+        },
+    };
+    let _ = UPDATES.send(PresenceUpdate {
+        team_id,
+        online,
+        audience,
+    });
+}
@@ -0,0 +1,81 @@
+use crate::utils::{
+    auth::Claims,
+    events::{Event, get_events_after},
+};
+use once_cell::sync::Lazy;
+use std::{
+    collections::{BTreeMap, HashMap},
+    sync::Mutex,
+};
+
+// The highest event ID each user has acknowledged; everything up to it is read.
+static CURSORS: Lazy<Mutex<HashMap<u64, u64>>> = Lazy::new(|| Mutex::new(HashMap::new()));
+
+fn cursors() -> std::sync::MutexGuard<'static, HashMap<u64, u64>> {
+    CURSORS.lock().unwrap_or_else(|e| e.into_inner())
+}
+
+pub struct Unread {
+    pub cursor: u64,
+    pub total: usize,
+    pub topics: BTreeMap<String, usize>,
+}
+
+pub fn cursor(user_id: u64) -> u64 {
+    cursors().get(&user_id).copied().unwrap_or(0)
+}
+
+// Acknowledging an older ID than the cursor, e.g. from a second tab, keeps it.
+pub fn acknowledge(user_id: u64, id: u64) -> u64 {
+    let mut cursors = cursors();
+    let cursor = cursors.entry(user_id).or_default();
+    *cursor = (*cursor).max(id);
+    *cursor
+}
+
+pub fn all_cursors() -> HashMap<u64, u64> {
+    cursors().clone()
+}
+
+// Cursors saved with a snapshot or kept by the inbox store.
+pub fn restore(saved: HashMap<u64, u64>) {
+    for (user_id, id) in saved {
+        acknowledge(user_id, id);
+    }
+}
+
+// Broadcasts are not counted.
+pub fn is_targeted(event: &Event, claims: &Claims) -> bool {
+    !event.audience.is_broadcast() && event.audience.matches(Some(claims))
+}
+
+pub async fn unread(claims: &Claims) -> Unread {
+    let cursor = cursor(claims.user_id);
+    let mut topics = BTreeMap::new();
+    let mut total = 0;
+    for event in get_events_after(cursor).await {
+        if is_targeted(&event, claims) {
+            *topics.entry(event.topic).or_default() += 1;
+            total += 1;
+        }
+    }
+    Unread {
+        cursor,
+        total,
+        topics,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_cursor_only_moves_forward() {
+        assert_eq!(cursor(900), 0);
+        assert_eq!(acknowledge(900, 12), 12);
+        assert_eq!(acknowledge(900, 7), 12);
+        assert_eq!(cursor(900), 12);
+        assert_eq!(cursor(901), 0);
+    }
+}
@@ -0,0 +1,114 @@
+use crate::utils::auth::Claims;
+use chrono::{DateTime, Utc};
+use once_cell::sync::Lazy;
+use serde::{Deserialize, Serialize};
+use std::{collections::HashMap, sync::Mutex};
+use tokio::sync::broadcast;
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum Subject {
+    #[serde(rename = "user_id")]
+    User(u64),
+    #[serde(rename = "team_id")]
+    Team(u64),
+    #[serde(rename = "jti")]
+    Token(String),
+}
+
+impl Subject {
+    pub fn applies_to(&self, claims: &Claims) -> bool {
+        match self {
+            Subject::User(id) => claims.user_id == *id,
+            Subject::Team(id) => claims.team_id == *id,
+            Subject::Token(jti) => claims.jti.as_deref() == Some(jti.as_str()),
+        }
+    }
+
+    pub fn parse(kind: &str, id: &str) -> Option<Subject> {
+        match kind {
+            "user" => id.parse().ok().map(Subject::User),
+            "team" => id.parse().ok().map(Subject::Team),
+            "jti" => Some(Subject::Token(id.to_string())),
+            _ => None,
+        }
+    }
+}
+
+static REVOKED: Lazy<Mutex<HashMap<Subject, DateTime<Utc>>>> =
+    Lazy::new(|| Mutex::new(HashMap::new()));
+
+// Open streams listen here and close themselves when a revocation matches.
+static REVOCATIONS: Lazy<broadcast::Sender<Subject>> = Lazy::new(|| broadcast::channel(64).0);
+
+fn revoked() -> std::sync::MutexGuard<'static, HashMap<Subject, DateTime<Utc>>> {
+    let mut revoked = REVOKED.lock().unwrap_or_else(|e| e.into_inner());
+    let now = Utc::now();
+    revoked.retain(|_, until| *until > now);
+    revoked
+}
+
+pub fn revoke(subject: Subject, until: DateTime<Utc>) {
+    revoked().insert(subject.clone(), until);
+    let _ = REVOCATIONS.send(subject);
+}
+
+pub fn lift(subject: &Subject) -> bool {
+    revoked().remove(subject).is_some()
+}
+
+pub fn is_revoked(claims: &Claims) -> bool {
+    revoked().keys().any(|subject| subject.applies_to(claims))
+}
+
+pub fn list() -> Vec<(Subject, DateTime<Utc>)> {
+    let mut entries: Vec<_> = revoked()
+        .iter()
+        .map(|(subject, until)| (subject.clone(), *until))
+        .collect();
+    entries.sort_by_key(|(_, until)| *until);
+    entries
+}
+
+pub fn subscribe() -> broadcast::Receiver<Subject> {
+    REVOCATIONS.subscribe()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serial_test::serial;
+
+    fn claims(user_id: u64, team_id: u64, jti: Option<&str>) -> Claims {
+        Claims {
+            jti: jti.map(str::to_string),
+            ..Claims::for_test(user_id, team_id)
+        }
+    }
+
+    #[test]
+    #[serial]
+    fn test_revocation_matches_and_expires() {
+        let later = Utc::now() + chrono::Duration::minutes(5);
+        revoke(Subject::Team(40), later);
+        revoke(Subject::Token("abc".into()), later);
+        revoke(Subject::User(41), Utc::now() - chrono::Duration::seconds(1));
+        assert!(is_revoked(&claims(1, 40, None)));
+        assert!(is_revoked(&claims(1, 2, Some("abc"))));
+        assert!(!is_revoked(&claims(41, 2, Some("def"))));
+        assert!(lift(&Subject::Team(40)));
+        assert!(!lift(&Subject::Team(40)));
+        assert!(!is_revoked(&claims(1, 40, None)));
+        lift(&Subject::Token("abc".into()));
+    }
+
+    #[test]
+    fn test_subject_parse() {
+        assert_eq!(Subject::parse("user", "7"), Some(Subject::User(7)));
+        assert_eq!(Subject::parse("team", "x"), None);
+        assert_eq!(
+            Subject::parse("jti", "abc"),
+            Some(Subject::Token("abc".into()))
+        );
+        assert_eq!(Subject::parse("device", "1"), None);
+    }
+}
@@ -0,0 +1,221 @@
+use crate::config::app::{ApiKey, DEFAULT_KEY_NAME, HttpConfig, SigningConfig};
+use actix_web::http::header::HeaderMap;
+use chrono::{DateTime, Utc};
+use hmac::{Hmac, Mac};
+use once_cell::sync::Lazy;
+use sha2::Sha256;
+use std::{collections::HashMap, sync::Mutex};
+
+type HmacSha256 = Hmac<Sha256>;
+
+pub const SIGNATURE_HEADER: &str = "x-signature";
+pub const TIMESTAMP_HEADER: &str = "x-timestamp";
+pub const NONCE_HEADER: &str = "x-nonce";
+pub const KEY_ID_HEADER: &str = "x-key-id";
+
+// Nonces are remembered until their timestamp falls outside the skew window,
+// after which the timestamp check alone rejects a replay.
+static SEEN_NONCES: Lazy<Mutex<HashMap<String, DateTime<Utc>>>> =
+    Lazy::new(|| Mutex::new(HashMap::new()));
+
+fn mac(secret: &str, timestamp: &str, nonce: &str, body: &[u8]) -> HmacSha256 {
+    let mut mac =
+        HmacSha256::new_from_slice(secret.as_bytes()).expect("HMAC accepts keys of any length");
+    mac.update(timestamp.as_bytes());
+    mac.update(b".");
+    mac.update(nonce.as_bytes());
+    mac.update(b".");
+    mac.update(body);
+    mac
+}
+
+// Producers sign `<timestamp>.<nonce>.<body>` with the raw key.
+pub fn sign(secret: &str, timestamp: &str, nonce: &str, body: &[u8]) -> String {
+    hex::encode(mac(secret, timestamp, nonce, body).finalize().into_bytes())
+}
+
+pub fn is_signed(headers: &HeaderMap) -> bool {
+    headers.contains_key(SIGNATURE_HEADER)
+}
+
+fn header<'a>(headers: &'a HeaderMap, name: &str) -> Result<&'a str, String> {
+    headers
+        .get(name)
+        .and_then(|v| v.to_str().ok())
+        .map(str::trim)
+        .filter(|v| !v.is_empty())
+        .ok_or_else(|| format!("Missing {} header", name))
+}
+
+pub fn verify(
+    http: &HttpConfig,
+    signing: &SigningConfig,
+    headers: &HeaderMap,
+    body: &[u8],
+    now: DateTime<Utc>,
+) -> Result<ApiKey, String> {
+    let signature = header(headers, SIGNATURE_HEADER)?;
+    let timestamp = header(headers, TIMESTAMP_HEADER)?;
+    let nonce = header(headers, NONCE_HEADER)?;
+    let key_id = headers
+        .get(KEY_ID_HEADER)
+        .and_then(|v| v.to_str().ok())
+        .unwrap_or(DEFAULT_KEY_NAME);
+    let key = http
+        .all_keys()
+        .into_iter()
+        .find(|k| k.name == key_id)
+        .ok_or("Invalid signature")?;
+    let signed_at = timestamp
+        .parse::<i64>()
+        .ok()
+        .and_then(|t| DateTime::<Utc>::from_timestamp(t, 0))
+        .ok_or("Invalid x-timestamp header")?;
+    let max_skew = chrono::Duration::from_std(signing.max_skew).unwrap_or(chrono::Duration::MAX);
+    if (now - signed_at).abs() > max_skew {
+        return Err("Request timestamp is outside the allowed window".into());
+    }
+    if nonce.len() < 8 || nonce.len() > 128 {
+        return Err("x-nonce must be between 8 and 128 characters".into());
+    }
+    let signature = hex::decode(signature).map_err(|_| "Invalid signature")?;
+    mac(&key.key, timestamp, nonce, body)
+        .verify_slice(&signature)
+        .map_err(|_| "Invalid signature")?;
+    // Only verified requests reach the cache, so it cannot be filled with junk.
+    let mut seen = SEEN_NONCES.lock().unwrap_or_else(|e| e.into_inner());
+    seen.retain(|_, expires| *expires > now);
+    let entry = format!("{}:{}", key.name, nonce);
+    if seen.contains_key(&entry) {
+        return Err("Nonce has already been used".into());
+    }
+    let expires = signed_at
+        .checked_add_signed(max_skew)
+        .unwrap_or(DateTime::<Utc>::MAX_UTC);
+    seen.insert(entry, expires);
+    Ok(key)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use actix_web::http::header::{HeaderName, HeaderValue};
+    use std::time::Duration;
+
+    const SECRET: &str = "core-key-0123456789";
+
+    fn http_config() -> HttpConfig {
+        toml::from_str(&format!(
+            r#"
+            endpoint = "/ingest"
+            [[keys]]
+            name = "rodan-core"
+            key = "{}"
+            "#,
+            SECRET
+        ))
+        .unwrap()
+    }
+
+    fn signing() -> SigningConfig {
+        SigningConfig {
+            required: true,
+            max_skew: Duration::from_secs(60),
+        }
+    }
+
+    fn headers(timestamp: i64, nonce: &str, signature: &str) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        for (name, value) in [
+            (SIGNATURE_HEADER, signature.to_string()),
+            (TIMESTAMP_HEADER, timestamp.to_string()),
+            (NONCE_HEADER, nonce.to_string()),
+            (KEY_ID_HEADER, "rodan-core".to_string()),
+        ] {
+            headers.insert(
+                HeaderName::from_static(name),
+                HeaderValue::from_str(&value).unwrap(),
+            );
+        }
+        headers
+    }
+
+    fn signed(body: &[u8], timestamp: i64, nonce: &str) -> HeaderMap {
+        let signature = sign(SECRET, &timestamp.to_string(), nonce, body);
+        headers(timestamp, nonce, &signature)
+    }
+
+    #[test]
+    fn test_valid_signature() {
+        let now = Utc::now();
+        let body = br#"{"events":["a"]}"#;
+        let nonce = format!("valid-{}", rand::random::<u64>());
+        let key = verify(
+            &http_config(),
+            &signing(),
+            &signed(body, now.timestamp(), &nonce),
+            body,
+            now,
+        )
+        .unwrap();
+        assert_eq!(key.name, "rodan-core");
+    }
+
+    #[test]
+    fn test_tampered_body_rejected() {
+        let now = Utc::now();
+        let nonce = format!("tamper-{}", rand::random::<u64>());
+        let headers = signed(br#"{"events":["a"]}"#, now.timestamp(), &nonce);
+        let err = verify(
+            &http_config(),
+            &signing(),
+            &headers,
+            br#"{"events":["b"]}"#,
+            now,
+        )
+        .unwrap_err();
+        assert_eq!(err, "Invalid signature");
+    }
+
+    #[test]
+    fn test_stale_timestamp_rejected() {
+        let now = Utc::now();
+        let body = b"{}";
+        let nonce = format!("stale-{}", rand::random::<u64>());
+        for timestamp in [now.timestamp() - 120, now.timestamp() + 120] {
+            let err = verify(
+                &http_config(),
+                &signing(),
+                &signed(body, timestamp, &nonce),
+                body,
+                now,
+            )
+            .unwrap_err();
+            assert!(err.contains("window"), "{}", err);
+        }
+    }
+
+    #[test]
+    fn test_unbounded_skew_does_not_overflow() {
+        let now = Utc::now();
+        let body = b"{}";
+        let nonce = format!("skew-{}", rand::random::<u64>());
+        let signing = SigningConfig {
+            required: true,
+            max_skew: Duration::from_secs(u64::MAX),
+        };
+        let headers = signed(body, now.timestamp(), &nonce);
+        assert!(verify(&http_config(), &signing, &headers, body, now).is_ok());
+    }
+
+    #[test]
+    fn test_replayed_nonce_rejected() {
+        let now = Utc::now();
+        let body = b"{}";
+        let nonce = format!("replay-{}", rand::random::<u64>());
+        let headers = signed(body, now.timestamp(), &nonce);
+        verify(&http_config(), &signing(), &headers, body, now).unwrap();
+        let err = verify(&http_config(), &signing(), &headers, body, now).unwrap_err();
+        assert_eq!(err, "Nonce has already been used");
+    }
+}
@@ -0,0 +1,182 @@
+use crate::utils::events::Event;
+use actix_web::web::Bytes;
+use serde::Deserialize;
+use serde_json::Value;
+use std::time::Duration;
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SseFormat {
+    #[default]
+    Sse,
+    Legacy,
+}
+
+#[derive(serde::Serialize)]
+struct LegacyMessage<'a> {
+    #[serde(rename = "type")]
+    event_type: &'a str,
+    data: &'a Value,
+}
+
+#[derive(Debug, Clone)]
+pub struct SseFrame {
+    pub id: Option<String>,
+    pub event: String,
+    pub data: Value,
+    pub retry: Option<Duration>,
+    // Sent instead of `data` in the legacy format.
+    pub legacy_data: Option<Value>,
+}
+
+impl SseFrame {
+    pub fn new(event: impl Into<String>, data: impl Into<Value>) -> Self {
+        Self {
+            id: None,
+            event: event.into(),
+            data: data.into(),
+            retry: None,
+            legacy_data: None,
+        }
+    }
+
+    pub fn with_id(mut self, id: impl ToString) -> Self {
+        self.id = Some(id.to_string());
+        self
+    }
+
+    pub fn with_retry(mut self, retry: Duration) -> Self {
+        self.retry = Some(retry);
+        self
+    }
+
+    // Carries only the reconnection delay, browsers apply it without dispatching an event.
+    pub fn retry_only(retry: Duration) -> Bytes {
+        Bytes::from(format!("retry: {}\n\n", retry.as_millis()))
+    }
+
+    pub fn encode(&self, format: SseFormat) -> Bytes {
+        match format {
+            SseFormat::Sse => self.encode_sse(),
+            SseFormat::Legacy => self.encode_legacy(),
+        }
+    }
+
+    fn encode_sse(&self) -> Bytes {
+        let mut out = String::new();
+        if let Some(id) = &self.id {
+            out.push_str(&format!("id: {}\n", strip_newlines(id)));
+        }
+        out.push_str(&format!("event: {}\n", strip_newlines(&self.event)));
+        let data = match &self.data {
+            Value::String(s) => s.clone(),
+            other => other.to_string(),
+        };
+        for line in data.split('\n') {
+            out.push_str(&format!("data: {}\n", line.trim_end_matches('\r')));
+        }
+        if let Some(retry) = self.retry {
+            out.push_str(&format!("retry: {}\n", retry.as_millis()));
+        }
+        out.push('\n');
+        Bytes::from(out)
+    }
+
+    fn encode_legacy(&self) -> Bytes {
+        let msg = LegacyMessage {
+            event_type: &self.event,
+            data: self.legacy_data.as_ref().unwrap_or(&self.data),
+        };
+        let payload = serde_json::to_string(&msg).unwrap_or_else(|_| "{}".into());
+        Bytes::from(format!("{}\n", payload))
+    }
+}
+
+impl From<&Event> for SseFrame {
+    fn from(event: &Event) -> Self {
+        let mut data = serde_json::to_value(&event.payload).unwrap_or_default();
+        if let Value::Object(fields) = &mut data {
+            fields.insert("topic".into(), Value::String(event.topic.clone()));
+        }
+        let mut frame = SseFrame::new("event", data).with_id(event.id);
+        // Legacy clients predate the envelope and still get plain strings as such.
+        frame.legacy_data = event.payload.plain_body().map(Value::from);
+        frame
+    }
+}
+
+fn strip_newlines(value: &str) -> String {
+    value.replace(['\r', '\n'], "")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn encoded(frame: &SseFrame, format: SseFormat) -> String {
+        String::from_utf8(frame.encode(format).to_vec()).unwrap()
+    }
+
+    #[test]
+    fn test_sse_frame_fields() {
+        let frame = SseFrame::new("event", "hello")
+            .with_id(42)
+            .with_retry(Duration::from_secs(3));
+        assert_eq!(
+            encoded(&frame, SseFormat::Sse),
+            "id: 42\nevent: event\ndata: hello\nretry: 3000\n\n"
+        );
+    }
+
+    #[test]
+    fn test_sse_frame_multiline_data() {
+        let frame = SseFrame::new("event", "line one\r\nline two");
+        assert_eq!(
+            encoded(&frame, SseFormat::Sse),
+            "event: event\ndata: line one\ndata: line two\n\n"
+        );
+    }
+
+    #[test]
+    fn test_sse_frame_json_data() {
+        let frame = SseFrame::new("event", json!({"title": "hi"}));
+        assert_eq!(
+            encoded(&frame, SseFormat::Sse),
+            "event: event\ndata: {\"title\":\"hi\"}\n\n"
+        );
+    }
+
+    #[test]
+    fn test_legacy_frame_matches_json_lines() {
+        let frame = SseFrame::new("heartbeat", "ping").with_id(7);
+        assert_eq!(
+            encoded(&frame, SseFormat::Legacy),
+            "{\"type\":\"heartbeat\",\"data\":\"ping\"}\n"
+        );
+    }
+
+    #[test]
+    fn test_legacy_frame_keeps_plain_strings() {
+        use crate::utils::events::EventDraft;
+        let event = |draft: EventDraft| Event {
+            payload: draft.payload,
+            ..Event::for_test(3)
+        };
+        let plain = SseFrame::from(&event(EventDraft::from("user_logged_in")));
+        assert_eq!(
+            encoded(&plain, SseFormat::Legacy),
+            "{\"type\":\"event\",\"data\":\"user_logged_in\"}\n"
+        );
+        let mut draft = EventDraft::from("Round 2");
+        draft.payload.title = Some("Rounds".into());
+        let titled = SseFrame::from(&event(draft));
+        assert!(encoded(&titled, SseFormat::Legacy).contains("\"title\":\"Rounds\""));
+    }
+
+    #[test]
+    fn test_retry_only_frame() {
+        let bytes = SseFrame::retry_only(Duration::from_millis(1500));
+        assert_eq!(&bytes[..], b"retry: 1500\n\n");
+    }
+}
@@ -0,0 +1,3 @@
+mod frame;
+
+pub use frame::{SseFormat, SseFrame};
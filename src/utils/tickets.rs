@@ -0,0 +1,61 @@
+use crate::utils::auth::Claims;
+use once_cell::sync::Lazy;
+use std::{
+    collections::HashMap,
+    sync::Mutex,
+    time::{Duration, Instant},
+};
+
+pub const TICKET_QUERY_PARAM: &str = "ticket";
+
+struct Ticket {
+    claims: Claims,
+    expires: Instant,
+}
+
+static TICKETS: Lazy<Mutex<HashMap<String, Ticket>>> = Lazy::new(|| Mutex::new(HashMap::new()));
+
+pub fn issue(claims: Claims, ttl: Duration) -> String {
+    let ticket = hex::encode(rand::random::<[u8; 32]>());
+    let now = Instant::now();
+    let mut tickets = TICKETS.lock().unwrap_or_else(|e| e.into_inner());
+    tickets.retain(|_, t| t.expires > now);
+    tickets.insert(
+        ticket.clone(),
+        Ticket {
+            claims,
+            expires: now + ttl,
+        },
+    );
+    ticket
+}
+
+// A ticket is removed on first use whether or not it is still valid.
+pub fn redeem(ticket: &str) -> Option<Claims> {
+    let mut tickets = TICKETS.lock().unwrap_or_else(|e| e.into_inner());
+    tickets
+        .remove(ticket)
+        .filter(|t| t.expires > Instant::now())
+        .map(|t| t.claims)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_ticket_is_single_use() {
+        let ticket = issue(Claims::for_test(1, 2), Duration::from_secs(10));
+        assert_eq!(ticket.len(), 64);
+        assert_eq!(redeem(&ticket).unwrap().team_id, 2);
+        assert!(redeem(&ticket).is_none());
+        assert!(redeem("unknown").is_none());
+    }
+
+    #[test]
+    fn test_expired_ticket_is_rejected() {
+        let ticket = issue(Claims::for_test(1, 2), Duration::from_millis(1));
+        std::thread::sleep(Duration::from_millis(5));
+        assert!(redeem(&ticket).is_none());
+    }
+}
@@ -12,4 +12,3 @@ pub fn set_config(cfg: Config) {
 pub fn get_config() -> Arc<Config> {
     GLOBAL_CONFIG.load_full()
 }
-
@@ -1,7 +1,35 @@
+use crate::{utils::events::Event, values::config::get_config};
 use once_cell::sync::Lazy;
+use std::{collections::HashMap, sync::RwLock};
 use tokio::sync::broadcast;
 
-pub static EVENT_CHANNEL: Lazy<broadcast::Sender<String>> = Lazy::new(|| {
-    let (tx, _) = broadcast::channel(100);
+fn channel_capacity() -> usize {
+    get_config().app.event_channel_capacity.unwrap_or(100)
+}
+
+pub static EVENT_CHANNEL: Lazy<broadcast::Sender<Event>> = Lazy::new(|| {
+    let (tx, _) = broadcast::channel(channel_capacity());
     tx
 });
+
+static TOPIC_CHANNELS: Lazy<RwLock<HashMap<String, broadcast::Sender<Event>>>> =
+    Lazy::new(|| RwLock::new(HashMap::new()));
+
+pub fn subscribe_topic(topic: &str) -> broadcast::Receiver<Event> {
+    let mut channels = TOPIC_CHANNELS.write().unwrap();
+    if let Some(tx) = channels.get(topic) {
+        return tx.subscribe();
+    }
+    channels.retain(|_, tx| tx.receiver_count() > 0);
+    let (tx, rx) = broadcast::channel(channel_capacity());
+    channels.insert(topic.to_string(), tx);
+    rx
+}
+
+pub fn publish(event: &Event) {
+    let _ = EVENT_CHANNEL.send(event.clone());
+    let channels = TOPIC_CHANNELS.read().unwrap();
+    if let Some(tx) = channels.get(&event.topic) {
+        let _ = tx.send(event.clone());
+    }
+}
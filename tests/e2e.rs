@@ -112,3 +112,47 @@ async fn test_events_since_last_received_update() {
         events_body
     );
 }
+
+#[tokio::test]
+#[serial]
+async fn test_structured_event_roundtrip() {
+    let host = env::var("RODAN_HOST").unwrap_or_else(|_| "http://localhost:8080".into());
+    let raw_key = env::var("RODAN_API_KEY").unwrap_or_else(|_| "1234567890123456".into());
+    let hashed_key = hash_key(&raw_key);
+
+    let client = Client::new();
+    let payload = json!({
+        "events": [{
+            "type": "announcement",
+            "title": "Round 2",
+            "body": "round_two_starting",
+            "severity": "warning",
+            "metadata": {"link": "/rounds/2"},
+            "source": "rodan-admin"
+        }]
+    });
+    let resp = client
+        .post(format!("{}/api/events/ingest", host))
+        .header("x-api-key", &hashed_key)
+        .json(&payload)
+        .send()
+        .await
+        .expect("Failed POST /api/events/ingest");
+    assert!(resp.status().is_success(), "Failed ingesting events");
+
+    let events: Vec<serde_json::Value> = client
+        .get(format!("{}/api/events", host))
+        .send()
+        .await
+        .expect("Failed GET /api/events")
+        .json()
+        .await
+        .unwrap();
+    let event = events
+        .iter()
+        .find(|e| e["message"]["body"] == "round_two_starting")
+        .expect("Structured event not returned");
+    assert_eq!(event["message"]["type"], "announcement");
+    assert_eq!(event["message"]["severity"], "warning");
+    assert_eq!(event["message"]["metadata"]["link"], "/rounds/2");
+}